@@ -0,0 +1,324 @@
+//! Controllers which are sent as sequences of control change messages.
+
+use crate::message::*;
+
+/// A 14 bit controller value which is sent as a pair of control change messages.
+///
+/// The most significant 7 bits are sent with one of the controllers 0 to 31 and the least
+/// significant 7 bits with the controller 32 above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ControlChange14 {
+    controller: u8,
+    value: u16,
+    channel: u8,
+}
+
+impl ControlChange14 {
+    pub fn new(controller: u64, value: u64, channel: u64) -> Self {
+        Self {
+            controller: controller.min(31) as u8,
+            value: value.min(16383) as u16,
+            channel: channel.min(15) as u8,
+        }
+    }
+    pub fn try_new(controller: u64, value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("controller", controller, 31)?,
+            check_range("value", value, 16383)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
+    /// The controller which carries the most significant bits.
+    pub fn controller(&self) -> u8 {
+        self.controller
+    }
+    pub fn value(&self) -> u16 {
+        self.value
+    }
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+    pub fn msb(&self) -> ControlChange {
+        ControlChange::new(
+            self.controller as u64,
+            (self.value >> 7) as u64,
+            self.channel as u64,
+        )
+    }
+    pub fn lsb(&self) -> ControlChange {
+        ControlChange::new(
+            self.controller as u64 + 32,
+            (self.value & 0x7F) as u64,
+            self.channel as u64,
+        )
+    }
+    /// The messages to send, the most significant bits come first.
+    pub fn to_messages(&self) -> [ControlChange; 2] {
+        [self.msb(), self.lsb()]
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_messages()
+            .iter()
+            .flat_map(|message| message.pack().to_vec())
+            .collect()
+    }
+}
+
+/// Merges pairs of control change messages into 14 bit controller values.
+///
+/// Values are tracked per channel and controller. Following the midi specification, a most
+/// significant byte resets the least significant byte to 0 and produces a value right away,
+/// a least significant byte produces a value when a most significant byte was received before it.
+#[derive(Debug, Default, Clone)]
+pub struct ControlChange14Decoder {
+    msb: [[Option<u8>; 32]; 16],
+}
+
+impl ControlChange14Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a message to the decoder and returns the value it completes.
+    ///
+    /// Messages other than control changes of the controllers 0 to 63 are ignored.
+    pub fn feed(&mut self, message: &Message) -> Option<ControlChange14> {
+        let Message::ControlChange(message) = message else {
+            return None;
+        };
+        let channel = message.channel() as usize;
+        match message.controller() {
+            controller @ 0..=31 => {
+                self.msb[channel][controller as usize] = Some(message.value());
+                Some(ControlChange14::new(
+                    controller as u64,
+                    (message.value() as u64) << 7,
+                    channel as u64,
+                ))
+            }
+            controller @ 32..=63 => {
+                let controller = controller - 32;
+                let msb = self.msb[channel][controller as usize]?;
+                Some(ControlChange14::new(
+                    controller as u64,
+                    ((msb as u64) << 7) | message.value() as u64,
+                    channel as u64,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The controllers which select and change parameter numbers.
+mod controllers {
+    pub const DATA_ENTRY_MSB: u8 = 6;
+    pub const DATA_ENTRY_LSB: u8 = 38;
+    pub const DATA_INCREMENT: u8 = 96;
+    pub const DATA_DECREMENT: u8 = 97;
+    pub const NRPN_LSB: u8 = 98;
+    pub const NRPN_MSB: u8 = 99;
+    pub const RPN_LSB: u8 = 100;
+    pub const RPN_MSB: u8 = 101;
+}
+
+/// The parameter number which deselects the current parameter, so that following data entry
+/// messages don't change it by accident.
+pub const NULL_PARAMETER_NUMBER: u16 = 0x3FFF;
+
+macro_rules! impl_parameter_number {
+    ($type:ident, $msb_controller:expr, $lsb_controller:expr) => {
+        impl $type {
+            pub fn new(parameter: u64, value: u64, channel: u64) -> Self {
+                Self {
+                    parameter: parameter.min(16383) as u16,
+                    value: value.min(16383) as u16,
+                    channel: channel.min(15) as u8,
+                }
+            }
+            pub fn try_new(parameter: u64, value: u64, channel: u64) -> Result<Self, RangeError> {
+                Ok(Self::new(
+                    check_range("parameter", parameter, 16383)?,
+                    check_range("value", value, 16383)?,
+                    check_range("channel", channel, 15)?,
+                ))
+            }
+            pub fn parameter(&self) -> u16 {
+                self.parameter
+            }
+            pub fn value(&self) -> u16 {
+                self.value
+            }
+            pub fn channel(&self) -> u8 {
+                self.channel
+            }
+            /// The control changes which select the parameter and set its value.
+            ///
+            /// When `terminate` is true the sequence ends with the null parameter number.
+            pub fn to_messages(&self, terminate: bool) -> Vec<ControlChange> {
+                let channel = self.channel as u64;
+                let mut messages = parameter_messages(
+                    $msb_controller,
+                    $lsb_controller,
+                    self.parameter,
+                    channel,
+                );
+                messages.push(ControlChange::new(
+                    controllers::DATA_ENTRY_MSB as u64,
+                    (self.value >> 7) as u64,
+                    channel,
+                ));
+                messages.push(ControlChange::new(
+                    controllers::DATA_ENTRY_LSB as u64,
+                    (self.value & 0x7F) as u64,
+                    channel,
+                ));
+                if terminate {
+                    messages.extend(parameter_messages(
+                        $msb_controller,
+                        $lsb_controller,
+                        NULL_PARAMETER_NUMBER,
+                        channel,
+                    ));
+                }
+                messages
+            }
+            pub fn to_bytes(&self, terminate: bool) -> Vec<u8> {
+                self.to_messages(terminate)
+                    .iter()
+                    .flat_map(|message| message.pack().to_vec())
+                    .collect()
+            }
+        }
+    };
+}
+
+fn parameter_messages(
+    msb_controller: u8,
+    lsb_controller: u8,
+    parameter: u16,
+    channel: u64,
+) -> Vec<ControlChange> {
+    vec![
+        ControlChange::new(msb_controller as u64, (parameter >> 7) as u64, channel),
+        ControlChange::new(lsb_controller as u64, (parameter & 0x7F) as u64, channel),
+    ]
+}
+
+/// A registered parameter number change, like the pitch bend range or fine and coarse tuning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rpn {
+    parameter: u16,
+    value: u16,
+    channel: u8,
+}
+
+impl_parameter_number!(Rpn, controllers::RPN_MSB, controllers::RPN_LSB);
+
+/// A non-registered parameter number change, their meaning is up to the manufacturer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Nrpn {
+    parameter: u16,
+    value: u16,
+    channel: u8,
+}
+
+impl_parameter_number!(Nrpn, controllers::NRPN_MSB, controllers::NRPN_LSB);
+
+/// A parameter change which [ParameterNumberDecoder] produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterChange {
+    Rpn(Rpn),
+    Nrpn(Nrpn),
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ParameterState {
+    registered: bool,
+    parameter_msb: Option<u8>,
+    parameter_lsb: Option<u8>,
+    value_msb: Option<u8>,
+    value: Option<u16>,
+}
+
+impl ParameterState {
+    fn select(&mut self, registered: bool, msb: Option<u8>, lsb: Option<u8>) {
+        if self.registered != registered {
+            self.parameter_msb = None;
+            self.parameter_lsb = None;
+        }
+        self.registered = registered;
+        self.parameter_msb = msb.or(self.parameter_msb);
+        self.parameter_lsb = lsb.or(self.parameter_lsb);
+        self.value_msb = None;
+        self.value = None;
+    }
+
+    fn parameter(&self) -> Option<u16> {
+        let parameter = ((self.parameter_msb? as u16) << 7) | self.parameter_lsb? as u16;
+        (parameter != NULL_PARAMETER_NUMBER).then_some(parameter)
+    }
+}
+
+/// Turns streams of control change messages into registered and non-registered parameter changes.
+///
+/// Parameters are tracked per channel. A change is produced for each data entry message once a
+/// parameter is selected, the most significant byte resets the least significant byte to 0.
+/// Data increment and decrement messages change the last value of the selected parameter.
+#[derive(Debug, Default, Clone)]
+pub struct ParameterNumberDecoder {
+    channels: [ParameterState; 16],
+}
+
+impl ParameterNumberDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a message to the decoder and returns the parameter change it completes.
+    ///
+    /// Messages other than the control changes of parameter numbers are ignored.
+    pub fn feed(&mut self, message: &Message) -> Option<ParameterChange> {
+        let Message::ControlChange(message) = message else {
+            return None;
+        };
+        let channel = message.channel();
+        let state = &mut self.channels[channel as usize];
+        let byte = message.value();
+        let value = match message.controller() {
+            controller @ (controllers::NRPN_LSB..=controllers::RPN_MSB) => {
+                let registered = controller >= controllers::RPN_LSB;
+                if controller == controllers::RPN_MSB || controller == controllers::NRPN_MSB {
+                    state.select(registered, Some(byte), None);
+                } else {
+                    state.select(registered, None, Some(byte));
+                }
+                return None;
+            }
+            controllers::DATA_ENTRY_MSB => {
+                state.value_msb = Some(byte);
+                (byte as u16) << 7
+            }
+            controllers::DATA_ENTRY_LSB => ((state.value_msb? as u16) << 7) | byte as u16,
+            controllers::DATA_INCREMENT => (state.value? + 1).min(16383),
+            controllers::DATA_DECREMENT => state.value?.saturating_sub(1),
+            _ => return None,
+        };
+        let parameter = state.parameter()?;
+        state.value = Some(value);
+        let (parameter, value, channel) = (parameter as u64, value as u64, channel as u64);
+        Some(if state.registered {
+            ParameterChange::Rpn(Rpn::new(parameter, value, channel))
+        } else {
+            ParameterChange::Nrpn(Nrpn::new(parameter, value, channel))
+        })
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,102 @@
+use crate::controller::*;
+use crate::KotoMessage;
+
+use koto::derive::*;
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+pub fn koto_control_change_14(value: ControlChange14) -> KMap {
+    let map = KMap::new();
+    map.insert("type", "control_change_14");
+    map.insert("controller", value.controller());
+    map.insert("value", value.value());
+    map.insert("channel", value.channel());
+    map
+}
+
+/// Returns the messages of a controller as a list of message objects.
+pub fn koto_messages<T: Into<crate::message::Message>>(
+    messages: impl IntoIterator<Item = T>,
+    strict: bool,
+) -> KValue {
+    let messages = messages
+        .into_iter()
+        .map(|message| KotoMessage::new(message.into()).with_strict(strict).into())
+        .collect::<Vec<KValue>>();
+    KValue::List(KList::from_slice(&messages[..]))
+}
+
+/// A 14 bit controller decoder which can be used from koto scripts.
+#[derive(Clone, Default, KotoType, KotoCopy)]
+#[koto(type_name = "ControlChange14Decoder")]
+pub struct KotoControlChange14Decoder {
+    decoder: ControlChange14Decoder,
+}
+
+#[koto_impl]
+impl KotoControlChange14Decoder {
+    #[koto_method]
+    fn feed(&mut self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        match args {
+            [KValue::Object(message)] if message.is_a::<KotoMessage>() => {
+                let message = message.cast::<KotoMessage>()?;
+                match self.decoder.feed(message.message()) {
+                    Some(value) => Ok(koto_control_change_14(value).into()),
+                    None => Ok(KValue::Null),
+                }
+            }
+            _ => runtime_error!("feed requires a single message as its argument"),
+        }
+    }
+
+    #[koto_method]
+    fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+impl KotoObject for KotoControlChange14Decoder {}
+
+pub fn koto_parameter_change(change: ParameterChange) -> KMap {
+    let (type_literal, parameter, value, channel) = match change {
+        ParameterChange::Rpn(rpn) => ("rpn", rpn.parameter(), rpn.value(), rpn.channel()),
+        ParameterChange::Nrpn(nrpn) => ("nrpn", nrpn.parameter(), nrpn.value(), nrpn.channel()),
+    };
+    let map = KMap::new();
+    map.insert("type", type_literal);
+    map.insert("parameter", parameter);
+    map.insert("value", value);
+    map.insert("channel", channel);
+    map
+}
+
+/// A registered and non-registered parameter number decoder which can be used from koto scripts.
+#[derive(Clone, Default, KotoType, KotoCopy)]
+#[koto(type_name = "ParameterNumberDecoder")]
+pub struct KotoParameterNumberDecoder {
+    decoder: ParameterNumberDecoder,
+}
+
+#[koto_impl]
+impl KotoParameterNumberDecoder {
+    #[koto_method]
+    fn feed(&mut self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        match args {
+            [KValue::Object(message)] if message.is_a::<KotoMessage>() => {
+                let message = message.cast::<KotoMessage>()?;
+                match self.decoder.feed(message.message()) {
+                    Some(change) => Ok(koto_parameter_change(change).into()),
+                    None => Ok(KValue::Null),
+                }
+            }
+            _ => runtime_error!("feed requires a single message as its argument"),
+        }
+    }
+
+    #[koto_method]
+    fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+impl KotoObject for KotoParameterNumberDecoder {}
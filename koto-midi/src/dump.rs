@@ -0,0 +1,192 @@
+//! System exclusive dumps which are received and sent in chunks.
+//!
+//! Drivers deliver large dumps in pieces of any size, [SysexAssembler] buffers them until the end
+//! of exclusive byte arrives. Some devices need dumps to be sent in packets with a delay between
+//! them, [split] breaks a message into packets of a given size.
+
+use crate::message::*;
+
+/// Something which happened to a dump while it was being assembled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssemblerEvent {
+    Complete(SystemExclusive),
+    /// The dump ended, but its bytes aren't a valid system exclusive message.
+    Malformed(ParseError),
+    /// A status byte other than a system realtime one arrived before the end of exclusive byte.
+    ///
+    /// The status byte is 0xF0 when the next dump started.
+    Aborted {
+        bytes: Vec<u8>,
+        status: u8,
+    },
+    /// The dump timed out or was flushed before its end of exclusive byte arrived.
+    Incomplete {
+        bytes: Vec<u8>,
+    },
+    /// The dump grew beyond the size limit, its bytes are discarded until it ends.
+    Overflowed {
+        max_size: usize,
+    },
+}
+
+/// Buffers the chunks of system exclusive dumps until they are complete.
+///
+/// Bytes outside of dumps and system realtime messages inside them are ignored.
+#[derive(Debug, Default, Clone)]
+pub struct SysexAssembler {
+    buffer: Vec<u8>,
+    receiving: bool,
+    overflowed: bool,
+    /// The largest dump which is buffered, including its start and end of exclusive bytes.
+    max_size: Option<usize>,
+    /// The number of chunks without bytes for a dump after which it is reported as incomplete.
+    timeout: Option<usize>,
+    idle_chunks: usize,
+}
+
+impl SysexAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    pub fn with_timeout(mut self, chunks: usize) -> Self {
+        self.timeout = Some(chunks);
+        self
+    }
+
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    pub fn timeout(&self) -> Option<usize> {
+        self.timeout
+    }
+
+    /// Whether a dump has started and not ended yet.
+    pub fn is_receiving(&self) -> bool {
+        self.receiving
+    }
+
+    /// The bytes of the dump which is being received.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Feeds a chunk of bytes and returns the events which it caused.
+    ///
+    /// A chunk which brings no bytes to the dump being received counts towards its timeout.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<AssemblerEvent> {
+        let mut events = vec![];
+        let mut received = false;
+        for &byte in bytes {
+            received |= self.push(byte, &mut events);
+        }
+        if received {
+            self.idle_chunks = 0;
+        } else if self.receiving {
+            self.idle_chunks += 1;
+            if self
+                .timeout
+                .is_some_and(|timeout| self.idle_chunks >= timeout)
+            {
+                events.extend(self.flush());
+            }
+        }
+        events
+    }
+
+    /// Ends the dump which is being received, returning it as incomplete.
+    ///
+    /// Overflowed dumps were already reported, so they are discarded quietly.
+    pub fn flush(&mut self) -> Option<AssemblerEvent> {
+        let bytes = self.finish();
+        if bytes.is_empty() {
+            None
+        } else {
+            Some(AssemblerEvent::Incomplete { bytes })
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self {
+            max_size: self.max_size,
+            timeout: self.timeout,
+            ..Self::default()
+        };
+    }
+
+    /// Clears the dump which is being received and returns its bytes, which are empty when it
+    /// overflowed.
+    fn finish(&mut self) -> Vec<u8> {
+        self.receiving = false;
+        self.overflowed = false;
+        self.idle_chunks = 0;
+        std::mem::take(&mut self.buffer)
+    }
+
+    /// Returns whether the byte belongs to a dump.
+    fn push(&mut self, byte: u8, events: &mut Vec<AssemblerEvent>) -> bool {
+        match byte {
+            0xF8..=0xFF => false,
+            0xF7 if self.receiving => {
+                let mut bytes = self.finish();
+                if !bytes.is_empty() {
+                    bytes.push(byte);
+                    events.push(match SystemExclusive::try_from(&bytes[..]) {
+                        Ok(message) => AssemblerEvent::Complete(message),
+                        Err(error) => AssemblerEvent::Malformed(error),
+                    });
+                }
+                true
+            }
+            0x80..=0xF7 => {
+                let bytes = self.finish();
+                if !bytes.is_empty() {
+                    events.push(AssemblerEvent::Aborted {
+                        bytes,
+                        status: byte,
+                    });
+                }
+                if byte == 0xF0 {
+                    self.receiving = true;
+                    self.buffer.push(byte);
+                }
+                byte == 0xF0
+            }
+            _ if !self.receiving => false,
+            _ if self.overflowed => true,
+            _ => {
+                // There has to be room for the end of exclusive byte.
+                if self
+                    .max_size
+                    .is_some_and(|max_size| self.buffer.len() + 2 > max_size)
+                {
+                    self.buffer.clear();
+                    self.overflowed = true;
+                    events.push(AssemblerEvent::Overflowed {
+                        max_size: self.max_size.unwrap_or_default(),
+                    });
+                } else {
+                    self.buffer.push(byte);
+                }
+                true
+            }
+        }
+    }
+}
+
+/// Splits the bytes of a message into packets of the given size, the last one can be shorter.
+///
+/// Sizes of 0 are treated as 1.
+pub fn split(message: &SystemExclusive, packet_size: usize) -> Vec<Vec<u8>> {
+    message
+        .pack()
+        .chunks(packet_size.max(1))
+        .map(|packet| packet.to_vec())
+        .collect()
+}
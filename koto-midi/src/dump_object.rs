@@ -0,0 +1,106 @@
+use crate::dump::*;
+use crate::message::*;
+use crate::message_object::byte_list;
+use crate::KotoMessage;
+
+use koto::derive::*;
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+pub fn koto_assembler_event(event: AssemblerEvent, strict: bool) -> KMap {
+    let map = KMap::new();
+    let koto_message =
+        |message: Message| KValue::from(KotoMessage::new(message).with_strict(strict));
+    match event {
+        AssemblerEvent::Complete(message) => {
+            map.insert("type", "complete");
+            map.insert("message", koto_message(message.into()));
+        }
+        AssemblerEvent::Malformed(error) => {
+            map.insert("type", "malformed");
+            map.insert("message", koto_message(Message::Malformed(error)));
+        }
+        AssemblerEvent::Aborted { bytes, status } => {
+            map.insert("type", "aborted");
+            map.insert("bytes", byte_list(&bytes));
+            map.insert("status", status);
+        }
+        AssemblerEvent::Incomplete { bytes } => {
+            map.insert("type", "incomplete");
+            map.insert("bytes", byte_list(&bytes));
+        }
+        AssemblerEvent::Overflowed { max_size } => {
+            map.insert("type", "overflowed");
+            map.insert("max_size", max_size as u64);
+        }
+    }
+    map
+}
+
+/// A system exclusive assembler which can be used from koto scripts.
+#[derive(Clone, KotoType, KotoCopy)]
+#[koto(type_name = "SysexAssembler")]
+pub struct KotoSysexAssembler {
+    assembler: SysexAssembler,
+    strict: bool,
+}
+
+impl KotoSysexAssembler {
+    pub fn new(assembler: SysexAssembler) -> Self {
+        Self {
+            assembler,
+            strict: false,
+        }
+    }
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+#[koto_impl]
+impl KotoSysexAssembler {
+    /// Returns a list of event maps, complete dumps are in the message field of their event.
+    #[koto_method]
+    fn feed(&mut self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let error = "feed requires a single list of zero or more bytes as its argument";
+        match args {
+            [KValue::List(bytes)] => {
+                let bytes = crate::collect_list_of_u8(bytes, error)?;
+                let events = self
+                    .assembler
+                    .feed(&bytes)
+                    .into_iter()
+                    .map(|event| koto_assembler_event(event, self.strict).into())
+                    .collect::<Vec<KValue>>();
+                Ok(KValue::List(KList::from_slice(&events[..])))
+            }
+            _ => runtime_error!(error),
+        }
+    }
+
+    #[koto_method]
+    fn flush(&mut self) -> KValue {
+        match self.assembler.flush() {
+            Some(event) => koto_assembler_event(event, self.strict).into(),
+            None => KValue::Null,
+        }
+    }
+
+    #[koto_method]
+    fn is_receiving(&self) -> KValue {
+        self.assembler.is_receiving().into()
+    }
+
+    #[koto_method]
+    fn buffered(&self) -> KValue {
+        byte_list(self.assembler.buffered())
+    }
+
+    #[koto_method]
+    fn reset(&mut self) {
+        self.assembler.reset();
+    }
+}
+
+impl KotoObject for KotoSysexAssembler {}
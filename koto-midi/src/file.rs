@@ -0,0 +1,525 @@
+//! Standard midi files.
+//!
+//! [MidiFile::parse] reads files of format 0, 1 and 2. Channel and meta events are classified
+//! the same way [ParsedMessage] classifies raw messages, meta events of unknown types and system
+//! exclusive escapes are kept with their raw data. [MidiFile::to_bytes] writes them back.
+
+use crate::message::*;
+use std::path::Path;
+
+/// The layout of the tracks in a standard midi file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A single track which contains every channel (format 0).
+    SingleTrack,
+    /// Tracks which play at the same time (format 1).
+    MultiTrack,
+    /// Tracks which are independent sequences (format 2).
+    MultiSequence,
+}
+
+impl Format {
+    pub fn number(&self) -> u16 {
+        match self {
+            Format::SingleTrack => 0,
+            Format::MultiTrack => 1,
+            Format::MultiSequence => 2,
+        }
+    }
+}
+
+/// The meaning of the ticks of the delta times in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timing {
+    TicksPerQuarterNote(u16),
+    /// Frames per second is one of 24, 25, 29 (30 drop frame) or 30.
+    Smpte {
+        frames_per_second: u8,
+        ticks_per_frame: u8,
+    },
+}
+
+/// The contents of the header chunk of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Header {
+    pub format: Format,
+    /// The number of tracks the header announces, the number of tracks which were read can differ.
+    pub track_count: u16,
+    pub timing: Timing,
+}
+
+/// What happens at a point in a track.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Message(Message),
+    /// A meta event which couldn't be classified, because its type is unknown or its data
+    /// is invalid.
+    Meta {
+        meta_type: u8,
+        data: Vec<u8>,
+    },
+    /// Bytes which are sent as they are, from `F7` events or `F0` events which don't contain
+    /// a complete system exclusive message (the first packet of a split message).
+    Escape(Vec<u8>),
+}
+
+impl EventKind {
+    fn is_end_of_track(&self) -> bool {
+        matches!(
+            self,
+            EventKind::Message(Message::EndOfTrack(_))
+                | EventKind::Meta {
+                    meta_type: EndOfTrack::META_TYPE,
+                    ..
+                }
+        )
+    }
+}
+
+/// An event in a track.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackEvent {
+    /// The ticks since the start of the track.
+    pub tick: u64,
+    /// The ticks since the previous event.
+    pub delta: u32,
+    pub kind: EventKind,
+}
+
+impl TrackEvent {
+    /// An event at a tick, the delta is left at 0 since [MidiFile::to_bytes] computes it.
+    pub fn new(tick: u64, kind: EventKind) -> Self {
+        Self {
+            tick,
+            delta: 0,
+            kind,
+        }
+    }
+}
+
+pub type Track = Vec<TrackEvent>;
+
+/// A standard midi file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MidiFile {
+    pub header: Header,
+    pub tracks: Vec<Track>,
+}
+
+/// The reason a file could not be read or written.
+///
+/// Offsets point to the byte in the file which caused the error.
+#[derive(Debug)]
+pub enum FileError {
+    Io(std::io::Error),
+    /// The file ends in the middle of a chunk or an event.
+    UnexpectedEnd {
+        offset: usize,
+    },
+    /// The file doesn't start with a header chunk.
+    MissingHeader,
+    /// The format in the header isn't 0, 1 or 2.
+    InvalidFormat {
+        format: u16,
+    },
+    /// A variable length quantity is longer than 4 bytes.
+    InvalidVariableLength {
+        offset: usize,
+    },
+    /// A data byte was found where a status byte was expected and there is no running status.
+    UnexpectedDataByte {
+        offset: usize,
+    },
+    /// The status byte can't start an event in a file.
+    UnexpectedStatus {
+        offset: usize,
+        status: u8,
+    },
+    /// Format 0 files have a single track and other files can have up to 65535 tracks.
+    InvalidTrackCount {
+        format: u16,
+        count: usize,
+    },
+    /// The ticks between two events of a track don't fit in a variable length quantity.
+    DeltaTimeTooLarge {
+        track: usize,
+        tick: u64,
+    },
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileError::Io(error) => write!(f, "{error}"),
+            FileError::UnexpectedEnd { offset } => {
+                write!(f, "the file ends unexpectedly at offset {offset}")
+            }
+            FileError::MissingHeader => write!(f, "the file doesn't start with a header chunk"),
+            FileError::InvalidFormat { format } => write!(f, "format {format} is not supported"),
+            FileError::InvalidVariableLength { offset } => {
+                write!(f, "invalid variable length quantity at offset {offset}")
+            }
+            FileError::UnexpectedDataByte { offset } => {
+                write!(
+                    f,
+                    "expected a status byte at offset {offset}, found a data byte"
+                )
+            }
+            FileError::UnexpectedStatus { offset, status } => {
+                write!(f, "unexpected status byte {status:#04X} at offset {offset}")
+            }
+            FileError::InvalidTrackCount { format: 0, count } => {
+                write!(f, "format 0 files have a single track, found {count}")
+            }
+            FileError::InvalidTrackCount { count, .. } => {
+                write!(f, "a file can have up to 65535 tracks, found {count}")
+            }
+            FileError::DeltaTimeTooLarge { track, tick } => {
+                write!(
+                    f,
+                    "the delta time before tick {tick} of track {track} is above {MAX_VARIABLE_LENGTH}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+impl From<std::io::Error> for FileError {
+    fn from(error: std::io::Error) -> Self {
+        FileError::Io(error)
+    }
+}
+
+/// Reads the chunks and events of a file while keeping track of the offset.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], FileError> {
+        let end = self.position + length;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(FileError::UnexpectedEnd {
+                offset: self.bytes.len(),
+            })?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, FileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FileError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, FileError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn variable_length(&mut self) -> Result<u32, FileError> {
+        let offset = self.position;
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(FileError::InvalidVariableLength { offset })
+    }
+
+    fn data(&mut self) -> Result<&'a [u8], FileError> {
+        let length = self.variable_length()?;
+        self.take(length as usize)
+    }
+}
+
+impl MidiFile {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, FileError> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, FileError> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4).ok() != Some(&b"MThd"[..]) {
+            return Err(FileError::MissingHeader);
+        }
+        let length = reader.u32()? as usize;
+        let header_end = reader.position + length;
+        let format = match reader.u16()? {
+            0 => Format::SingleTrack,
+            1 => Format::MultiTrack,
+            2 => Format::MultiSequence,
+            format => return Err(FileError::InvalidFormat { format }),
+        };
+        let track_count = reader.u16()?;
+        let division = reader.u16()?;
+        let timing = if division & 0x8000 == 0 {
+            Timing::TicksPerQuarterNote(division)
+        } else {
+            Timing::Smpte {
+                frames_per_second: ((division >> 8) as u8 as i8).unsigned_abs(),
+                ticks_per_frame: division as u8,
+            }
+        };
+        // Later versions of the format may add to the header.
+        reader.position = header_end.max(reader.position);
+
+        let mut tracks = vec![];
+        while reader.position < bytes.len() {
+            let id = reader.take(4)?;
+            let length = reader.u32()? as usize;
+            let start = reader.position;
+            let chunk = reader.take(length)?;
+            // Chunks of unknown types are skipped.
+            if id == b"MTrk" {
+                tracks.push(parse_track(chunk, start)?);
+            }
+        }
+
+        Ok(MidiFile {
+            header: Header {
+                format,
+                track_count,
+                timing,
+            },
+            tracks,
+        })
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>, running_status: bool) -> Result<(), FileError> {
+        Ok(std::fs::write(path, self.to_bytes(running_status)?)?)
+    }
+
+    /// Encodes the file.
+    ///
+    /// The header announces the tracks which are written, not the track count of [Header].
+    /// The events of a track are written in the order of their ticks and their deltas are
+    /// computed from the ticks. Every track ends with a single end of track event at its last tick.
+    /// When `running_status` is true, status bytes which repeat the previous one are left out.
+    ///
+    /// Files with a number of tracks which their format can't have, and tracks with gaps between
+    /// events which are too long for a delta time can't be written.
+    pub fn to_bytes(&self, running_status: bool) -> Result<Vec<u8>, FileError> {
+        let format = self.header.format.number();
+        let invalid_track_count = FileError::InvalidTrackCount {
+            format,
+            count: self.tracks.len(),
+        };
+        let Ok(track_count) = u16::try_from(self.tracks.len()) else {
+            return Err(invalid_track_count);
+        };
+        if self.header.format == Format::SingleTrack && track_count > 1 {
+            return Err(invalid_track_count);
+        }
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend_from_slice(&6u32.to_be_bytes());
+        bytes.extend_from_slice(&format.to_be_bytes());
+        bytes.extend_from_slice(&track_count.to_be_bytes());
+        let division = match self.header.timing {
+            Timing::TicksPerQuarterNote(ticks) => ticks & 0x7FFF,
+            Timing::Smpte {
+                frames_per_second,
+                ticks_per_frame,
+            } => u16::from_be_bytes([
+                (frames_per_second as i8).wrapping_neg() as u8,
+                ticks_per_frame,
+            ]),
+        };
+        bytes.extend_from_slice(&division.to_be_bytes());
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            let chunk = write_track(track, running_status)
+                .map_err(|tick| FileError::DeltaTimeTooLarge { track: index, tick })?;
+            bytes.extend_from_slice(b"MTrk");
+            bytes.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+}
+
+fn write_data(bytes: &mut Vec<u8>, data: &[u8]) {
+    write_variable_length(bytes, data.len() as u32);
+    bytes.extend_from_slice(data);
+}
+
+/// Encodes the events of a track chunk.
+///
+/// Returns the tick of the event whose delta time is too large for a variable length quantity.
+fn write_track(track: &Track, running_status: bool) -> Result<Vec<u8>, u64> {
+    let mut events = track.iter().collect::<Vec<&TrackEvent>>();
+    events.sort_by_key(|event| event.tick);
+    let end_tick = events.last().map_or(0, |event| event.tick);
+    let end_of_track = EventKind::Message(EndOfTrack::new().into());
+    let events = events
+        .iter()
+        .map(|event| (event.tick, &event.kind))
+        .filter(|(_, kind)| !kind.is_end_of_track())
+        .chain(std::iter::once((end_tick, &end_of_track)));
+
+    let mut bytes = vec![];
+    let mut previous_status = None;
+    let mut previous_tick = 0;
+    for (tick, kind) in events {
+        let delta = match u32::try_from(tick - previous_tick) {
+            Ok(delta) if delta <= MAX_VARIABLE_LENGTH => delta,
+            _ => return Err(tick),
+        };
+        let mut event = vec![];
+        match kind {
+            EventKind::Message(message) => match message.pack() {
+                [] => continue,
+                [status @ 0x80..=0xEF, data @ ..] => {
+                    if !running_status || previous_status != Some(*status) {
+                        event.push(*status);
+                    }
+                    event.extend_from_slice(data);
+                    previous_status = Some(*status);
+                }
+                // Meta events are written as they are.
+                meta_event @ [0xFF, _, ..] => {
+                    event.extend_from_slice(meta_event);
+                    previous_status = None;
+                }
+                [0xF0, data @ ..] => {
+                    event.push(0xF0);
+                    write_data(&mut event, data);
+                    previous_status = None;
+                }
+                // System common and realtime messages can only be stored as escapes.
+                raw_message => {
+                    event.push(0xF7);
+                    write_data(&mut event, raw_message);
+                    previous_status = None;
+                }
+            },
+            EventKind::Meta { meta_type, data } => {
+                event.extend_from_slice(&[0xFF, *meta_type]);
+                write_data(&mut event, data);
+                previous_status = None;
+            }
+            EventKind::Escape(data) => {
+                // The first packet of a split system exclusive message keeps its F0 status.
+                match data.split_first() {
+                    Some((0xF0, data)) => {
+                        event.push(0xF0);
+                        write_data(&mut event, data);
+                    }
+                    _ => {
+                        event.push(0xF7);
+                        write_data(&mut event, data);
+                    }
+                }
+                previous_status = None;
+            }
+        }
+        write_variable_length(&mut bytes, delta);
+        bytes.extend_from_slice(&event);
+        previous_tick = tick;
+    }
+    Ok(bytes)
+}
+
+/// Parses the events of a track chunk which starts at the given offset in the file.
+fn parse_track(chunk: &[u8], chunk_offset: usize) -> Result<Track, FileError> {
+    let mut reader = Reader {
+        bytes: chunk,
+        position: 0,
+    };
+    let mut events = vec![];
+    let mut running_status = None;
+    let mut tick = 0;
+
+    let offset_error = |error: FileError| match error {
+        FileError::UnexpectedEnd { offset } => FileError::UnexpectedEnd {
+            offset: offset + chunk_offset,
+        },
+        FileError::InvalidVariableLength { offset } => FileError::InvalidVariableLength {
+            offset: offset + chunk_offset,
+        },
+        error => error,
+    };
+
+    while reader.position < chunk.len() {
+        let delta = reader.variable_length().map_err(offset_error)?;
+        tick += delta as u64;
+        let offset = reader.position;
+        let status = match reader.bytes.get(offset) {
+            Some(&status) if status >= 0x80 => {
+                reader.position += 1;
+                status
+            }
+            _ => running_status.ok_or(FileError::UnexpectedDataByte {
+                offset: offset + chunk_offset,
+            })?,
+        };
+
+        let kind = match status {
+            0xFF => {
+                running_status = None;
+                let meta_type = reader.u8().map_err(offset_error)?;
+                let data = reader.data().map_err(offset_error)?.to_vec();
+                let mut raw_message = vec![0xFF, meta_type];
+                write_variable_length(&mut raw_message, data.len() as u32);
+                raw_message.extend_from_slice(&data);
+                match ParsedMessage::from(&raw_message[..]).message {
+                    Message::Malformed(_) => EventKind::Meta { meta_type, data },
+                    message => EventKind::Message(message),
+                }
+            }
+            0xF0 => {
+                running_status = None;
+                let mut raw_message = vec![0xF0];
+                raw_message.extend_from_slice(reader.data().map_err(offset_error)?);
+                let parsed = ParsedMessage::from(&raw_message[..]);
+                // Realtime bytes in the data would be lost if it was read as a message.
+                match parsed.message {
+                    _ if !parsed.realtime.is_empty() => EventKind::Escape(raw_message),
+                    Message::Malformed(_) => EventKind::Escape(raw_message),
+                    message => EventKind::Message(message),
+                }
+            }
+            0xF7 => {
+                running_status = None;
+                EventKind::Escape(reader.data().map_err(offset_error)?.to_vec())
+            }
+            0x80..=0xEF => {
+                running_status = Some(status);
+                let length = message_length(status).unwrap_or(1);
+                let mut raw_message = vec![status];
+                raw_message.extend_from_slice(reader.take(length - 1).map_err(offset_error)?);
+                let parsed = ParsedMessage::from(&raw_message[..]);
+                if parsed.realtime.is_empty() {
+                    EventKind::Message(parsed.message)
+                } else {
+                    EventKind::Escape(raw_message)
+                }
+            }
+            status => {
+                return Err(FileError::UnexpectedStatus {
+                    offset: offset + chunk_offset,
+                    status,
+                })
+            }
+        };
+
+        let end_of_track = kind.is_end_of_track();
+        events.push(TrackEvent { tick, delta, kind });
+        if end_of_track {
+            break;
+        }
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,316 @@
+use crate::file::*;
+use crate::message::*;
+use crate::message_object::byte_list;
+use crate::KotoMessage;
+use crate::ModuleSettings;
+
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+fn koto_event(event: TrackEvent, settings: ModuleSettings) -> KValue {
+    let map = KMap::new();
+    map.insert("tick", event.tick);
+    map.insert("delta", event.delta);
+    match event.kind {
+        EventKind::Message(message) => {
+            map.insert(
+                "message",
+                KotoMessage::new(message).with_strict(settings.strict),
+            );
+        }
+        EventKind::Meta { meta_type, data } => {
+            map.insert("meta_type", meta_type);
+            map.insert("data", byte_list(&data));
+        }
+        EventKind::Escape(bytes) => {
+            map.insert("escape", byte_list(&bytes));
+        }
+    }
+    map.into()
+}
+
+fn koto_file(file: MidiFile, settings: ModuleSettings) -> KValue {
+    let map = KMap::new();
+    map.insert("format", file.header.format.number());
+    map.insert("track_count", file.header.track_count);
+    match file.header.timing {
+        Timing::TicksPerQuarterNote(ticks) => {
+            map.insert("ticks_per_quarter_note", ticks);
+        }
+        Timing::Smpte {
+            frames_per_second,
+            ticks_per_frame,
+        } => {
+            let smpte = KMap::new();
+            smpte.insert("frames_per_second", frames_per_second);
+            smpte.insert("ticks_per_frame", ticks_per_frame);
+            map.insert("smpte", smpte);
+        }
+    }
+    let tracks = file
+        .tracks
+        .into_iter()
+        .map(|track| {
+            let events = track
+                .into_iter()
+                .map(|event| koto_event(event, settings))
+                .collect::<Vec<KValue>>();
+            KValue::List(KList::from_slice(&events[..]))
+        })
+        .collect::<Vec<KValue>>();
+    map.insert("tracks", KValue::List(KList::from_slice(&tracks[..])));
+    map.into()
+}
+
+/// Reads an optional number field of a song or an event.
+fn number_field(
+    map: &KMap,
+    key: &str,
+    max: u64,
+    context: &str,
+) -> Result<Option<u64>, RuntimeError> {
+    match map.get(key) {
+        Some(KValue::Number(KNumber::I64(value))) if value >= 0 && value as u64 <= max => {
+            Ok(Some(value as u64))
+        }
+        Some(_) => runtime_error!("{context}: {key} must be an integer between 0 and {max}"),
+        None => Ok(None),
+    }
+}
+
+fn bytes_field(map: &KMap, key: &str, context: &str) -> Result<Option<Vec<u8>>, RuntimeError> {
+    let error = format!("{context}: {key} must be a list of bytes");
+    match map.get(key) {
+        Some(KValue::List(bytes)) => Ok(Some(crate::collect_list_of_u8(&bytes, &error)?)),
+        Some(_) => runtime_error!(error),
+        None => Ok(None),
+    }
+}
+
+/// Builds an event from a map like the ones [koto_event] returns, the delta is ignored.
+///
+/// Tempo, time signature and track name meta events can also be given by their values, which
+/// are checked instead of clamped when the module is strict.
+fn event_from_map(event: &KMap, strict: bool, context: &str) -> Result<TrackEvent, RuntimeError> {
+    let Some(tick) = number_field(event, "tick", u64::MAX, context)? else {
+        return runtime_error!("{context}: an event requires a tick");
+    };
+    let kind = if let Some(message) = event.get("message") {
+        match message {
+            KValue::Object(message) if message.is_a::<KotoMessage>() => {
+                EventKind::Message(message.cast::<KotoMessage>()?.message().clone())
+            }
+            _ => return runtime_error!("{context}: message must be a message object"),
+        }
+    } else if let Some(meta_type) = number_field(event, "meta_type", 127, context)? {
+        EventKind::Meta {
+            meta_type: meta_type as u8,
+            data: bytes_field(event, "data", context)?.unwrap_or_default(),
+        }
+    } else if let Some(bytes) = bytes_field(event, "escape", context)? {
+        EventKind::Escape(bytes)
+    } else if let Some(tempo) = number_field(event, "tempo", 0xFFFFFF, context)? {
+        if strict && tempo == 0 {
+            return runtime_error!("{context}: tempo must be between 1 and 16777215, found 0");
+        }
+        EventKind::Message(Tempo::new(tempo).into())
+    } else if let Some(time_signature) = event.get("time_signature") {
+        let error = format!(
+            "{context}: time_signature must be a list of a numerator and a denominator, \
+             optionally followed by the clocks per click and the 32nd notes per quarter note"
+        );
+        let KValue::List(values) = time_signature else {
+            return runtime_error!(error);
+        };
+        let (numerator, denominator, clocks_per_click, thirty_seconds_per_quarter_note) =
+            match crate::collect_list_of_u8(&values, &error)?[..] {
+                [numerator, denominator] => (numerator, denominator, 24, 8),
+                [numerator, denominator, clocks_per_click, thirty_seconds_per_quarter_note] => (
+                    numerator,
+                    denominator,
+                    clocks_per_click,
+                    thirty_seconds_per_quarter_note,
+                ),
+                _ => return runtime_error!(error),
+            };
+        let time_signature = if strict {
+            match TimeSignature::try_new(
+                numerator as u64,
+                denominator as u64,
+                clocks_per_click as u64,
+                thirty_seconds_per_quarter_note as u64,
+            ) {
+                Ok(time_signature) => time_signature,
+                Err(error) => return runtime_error!("{context}: time_signature: {error}"),
+            }
+        } else {
+            TimeSignature::new(
+                numerator as u64,
+                denominator as u64,
+                clocks_per_click as u64,
+                thirty_seconds_per_quarter_note as u64,
+            )
+        };
+        EventKind::Message(time_signature.into())
+    } else if let Some(name) = event.get("track_name") {
+        match name {
+            KValue::Str(name) => EventKind::Message(TrackName::new(name.as_str()).into()),
+            _ => return runtime_error!("{context}: track_name must be a string"),
+        }
+    } else {
+        return runtime_error!(
+            "{context}: an event requires a message, a meta_type, an escape, \
+             a tempo, a time_signature or a track_name"
+        );
+    };
+    Ok(TrackEvent::new(tick, kind))
+}
+
+/// Builds a file from a map like the ones [koto_file] returns, along with whether running status
+/// is used when it is written.
+pub(crate) fn song_from_map(
+    song: &KMap,
+    strict: bool,
+    context: &str,
+) -> Result<(MidiFile, bool), RuntimeError> {
+    let Some(KValue::List(tracks)) = song.get("tracks") else {
+        return runtime_error!("{context}: a song requires a list of tracks");
+    };
+    let tracks = tracks
+        .data()
+        .iter()
+        .map(|track| match track {
+            KValue::List(events) => events
+                .data()
+                .iter()
+                .map(|event| match event {
+                    KValue::Map(event) => event_from_map(event, strict, context),
+                    _ => runtime_error!("{context}: an event must be a map"),
+                })
+                .collect::<Result<Track, RuntimeError>>(),
+            _ => runtime_error!("{context}: a track must be a list of events"),
+        })
+        .collect::<Result<Vec<Track>, RuntimeError>>()?;
+
+    let format = match number_field(song, "format", 2, context)? {
+        Some(0) if tracks.len() > 1 => {
+            return runtime_error!(
+                "{context}: format 0 songs have a single track, found {}",
+                tracks.len()
+            )
+        }
+        Some(0) => Format::SingleTrack,
+        Some(1) => Format::MultiTrack,
+        Some(_) => Format::MultiSequence,
+        None if tracks.len() == 1 => Format::SingleTrack,
+        None => Format::MultiTrack,
+    };
+    let timing = match song.get("smpte") {
+        Some(KValue::Map(smpte)) => {
+            let frames_per_second = match number_field(&smpte, "frames_per_second", 30, context)? {
+                Some(frames_per_second @ (24 | 25 | 29 | 30)) => frames_per_second as u8,
+                _ => {
+                    return runtime_error!(
+                        "{context}: frames_per_second must be one of 24, 25, 29 or 30"
+                    )
+                }
+            };
+            let ticks_per_frame = match number_field(&smpte, "ticks_per_frame", 255, context)? {
+                Some(0) => {
+                    return runtime_error!("{context}: ticks_per_frame must be between 1 and 255")
+                }
+                Some(ticks_per_frame) => ticks_per_frame,
+                None => return runtime_error!("{context}: smpte requires ticks_per_frame"),
+            };
+            Timing::Smpte {
+                frames_per_second,
+                ticks_per_frame: ticks_per_frame as u8,
+            }
+        }
+        Some(_) => return runtime_error!("{context}: smpte must be a map"),
+        None => match number_field(song, "ticks_per_quarter_note", 0x7FFF, context)? {
+            Some(0) => {
+                return runtime_error!(
+                    "{context}: ticks_per_quarter_note must be between 1 and 32767"
+                )
+            }
+            ticks_per_quarter_note => {
+                Timing::TicksPerQuarterNote(ticks_per_quarter_note.unwrap_or(480) as u16)
+            }
+        },
+    };
+    let running_status = match song.get("running_status") {
+        Some(KValue::Bool(running_status)) => running_status,
+        Some(_) => return runtime_error!("{context}: running_status must be a bool"),
+        None => true,
+    };
+
+    let Ok(track_count) = u16::try_from(tracks.len()) else {
+        return runtime_error!(
+            "{context}: a song can have up to 65535 tracks, found {}",
+            tracks.len()
+        );
+    };
+    let file = MidiFile {
+        header: Header {
+            format,
+            track_count,
+            timing,
+        },
+        tracks,
+    };
+    Ok((file, running_status))
+}
+
+pub fn make_file_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+
+    module.add_fn("read", move |ctx| match ctx.args() {
+        [KValue::Str(path)] => match MidiFile::read(path.as_str()) {
+            Ok(file) => Ok(koto_file(file, settings)),
+            Err(error) => runtime_error!("midi.file.read: Unable to read file '{path}': {error}"),
+        },
+        _ => runtime_error!("read requires a path as its argument"),
+    });
+
+    module.add_fn("parse", move |ctx| {
+        let error_literal = "parse requires a single list of bytes as its argument";
+        match ctx.args() {
+            [KValue::List(bytes)] => {
+                let bytes = crate::collect_list_of_u8(bytes, error_literal)?;
+                match MidiFile::parse(&bytes[..]) {
+                    Ok(file) => Ok(koto_file(file, settings)),
+                    Err(error) => runtime_error!("midi.file.parse: {error}"),
+                }
+            }
+            _ => runtime_error!(error_literal),
+        }
+    });
+
+    module.add_fn("write", move |ctx| match ctx.args() {
+        [KValue::Str(path), KValue::Map(song)] => {
+            let (file, running_status) = song_from_map(song, settings.strict, "midi.file.write")?;
+            match file.write(path.as_str(), running_status) {
+                Ok(()) => Ok(KValue::Null),
+                Err(error) => {
+                    runtime_error!("midi.file.write: Unable to write file '{path}': {error}")
+                }
+            }
+        }
+        _ => runtime_error!("write requires a path and a song map as its arguments"),
+    });
+
+    module.add_fn("to_bytes", move |ctx| match ctx.args() {
+        [KValue::Map(song)] => {
+            let (file, running_status) =
+                song_from_map(song, settings.strict, "midi.file.to_bytes")?;
+            match file.to_bytes(running_status) {
+                Ok(bytes) => Ok(byte_list(&bytes)),
+                Err(error) => runtime_error!("midi.file.to_bytes: {error}"),
+            }
+        }
+        _ => runtime_error!("to_bytes requires a song map as its argument"),
+    });
+
+    module
+}
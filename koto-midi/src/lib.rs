@@ -1,6 +1,53 @@
-mod message;
+//! A koto module for working with midi messages.
+//!
+//! [make_module] builds the `midi` module for koto scripts. The message types which scripts
+//! work with are also available to rust code through the [message] module.
+//!
+//! ```
+//! use koto_midi::message::{Category, Message, NoteOn};
+//!
+//! let message = Message::parse(&[0x91, 60, 100]).unwrap();
+//! assert_eq!(message, Message::from(NoteOn::new(60, 100, 1)));
+//! assert_eq!(message.category(), Category::ChannelVoice);
+//! assert_eq!(message.to_bytes(), vec![0x91, 60, 100]);
+//! ```
+
+#![allow(clippy::result_large_err)]
+
+pub mod controller;
+mod controller_object;
+pub mod dump;
+mod dump_object;
+pub mod file;
+mod file_module;
+pub mod manufacturer;
+pub mod message;
+mod message_object;
+pub mod mmc;
+mod mmc_module;
+pub mod mtc;
+mod mtc_module;
+pub mod mts;
+mod mts_module;
+pub mod packing;
+pub mod scala;
+mod scala_module;
+pub mod sysex;
+mod sysex_module;
+pub mod tempo;
+mod tempo_object;
+pub mod vendor;
+mod vendor_module;
+pub use message::MidiMessage;
+pub use message_object::KotoMessage;
+use controller::*;
+use controller_object::*;
+use message_object::{check_manufacturer_id, check_sysex_content, message_from_map};
 use message::*;
+use tempo::TempoMap;
+use tempo_object::KotoTempoMap;
 
+use koto::derive::*;
 use koto::prelude::*;
 use koto::runtime::KList;
 use koto::runtime::KNumber;
@@ -71,6 +118,27 @@ pub fn collect_list_of_u64(
     arguments
 }
 
+/// Collects the values of the given fields from a map, fields which aren't in the map are `None`.
+pub fn collect_fields_of_u64(
+    fields: &KMap,
+    names: &[&str],
+    error: &str,
+) -> std::result::Result<Vec<Option<u64>>, RuntimeError> {
+    for key in fields.data().keys() {
+        if !matches!(key.value(), KValue::Str(name) if names.contains(&name.as_str())) {
+            return runtime_error!(error);
+        }
+    }
+    names
+        .iter()
+        .map(|name| match fields.get(*name) {
+            Some(KValue::Number(KNumber::I64(value))) if value >= 0 => Ok(Some(value as u64)),
+            Some(_) => runtime_error!(error),
+            None => Ok(None),
+        })
+        .collect()
+}
+
 pub fn collect_list_of_value_list(
     message: &KList,
     error: &str,
@@ -112,42 +180,28 @@ macro_rules! types {
     }
 }
 
-macro_rules! impl_pack {
-    ($map:ident, $message:ident) => {
-        $map.add_fn("pack", move |_| {
-            Ok(KValue::List(KList::from_slice(
-                &$message
-                    .pack()
-                    .into_iter()
-                    .map(|byte| byte.into())
-                    .collect::<Vec<KValue>>()[..],
-            )))
-        });
-    };
-}
-
 macro_rules! make_koto_message_constructor {
-    ($map:ident, $enum_key:ident, $category_literal:literal, $($field:ident),*, $error_literal:literal) => {
+    ($map:ident, $settings:ident, $enum_key:ident, $category_literal:literal, $($field:ident),*, $error_literal:literal) => {
         let name_literal = pascal_case_to_underscore_separated_literal(stringify!($enum_key));
         $map.add_fn(&name_literal.clone(), move |ctx| {
             let args = ctx.args();
-            if args.len() == 1 {
+            if args.is_empty() {
+                let message = <$enum_key>::default();
+                Ok(KotoMessage::new(message.into()).with_strict($settings.strict).into())
+            } else if args.len() == 1 {
                 match args {
                     [KValue::List(message)] => {
                         if let Ok(arguments) = collect_list_of_u64(message, $error_literal) {
                             if let [$($field),*] = &arguments[..] {
-                                let message_koto = KMap::new();
-                                // dbg!($(*$field),*);
-                                let message = <$enum_key>::new($(*$field),*);
-                                // dbg!(&message);
-                                message_koto.insert("type", name_literal.clone());
-                                message_koto.insert("category", $category_literal);
-                                $(
-                                //   dbg!($field);
-                                  message_koto.insert(stringify!($field), message.$field());
-                                )*
-                                impl_pack!(message_koto, message);
-                                Ok(KValue::Map(message_koto))
+                                let message = if $settings.strict {
+                                    match <$enum_key>::try_new($(*$field),*) {
+                                        Ok(message) => message,
+                                        Err(error) => return runtime_error!("{name_literal}: {error}"),
+                                    }
+                                } else {
+                                    <$enum_key>::new($(*$field),*)
+                                };
+                                Ok(KotoMessage::new(message.into()).with_strict($settings.strict).into())
                             }
                             else {
                             runtime_error!($error_literal)
@@ -156,6 +210,7 @@ macro_rules! make_koto_message_constructor {
                             Ok(KValue::Null)
                         }
                     }
+                    [KValue::Map(fields)] => Ok(message_from_map(&name_literal, fields, $settings.strict)?.into()),
                     _ => runtime_error!($error_literal),
                 }
             } else {
@@ -164,17 +219,13 @@ macro_rules! make_koto_message_constructor {
         })
     };
 
-    ($map:ident, $enum_key:ty, $category_literal:literal, $error_literal:literal) => {
+    ($map:ident, $settings:ident, $enum_key:ty, $category_literal:literal, $error_literal:literal) => {
         let name_literal = pascal_case_to_underscore_separated_literal(stringify!($enum_key));
         $map.add_fn(&name_literal.clone(), move |ctx| {
             let args = ctx.args();
             if args.is_empty() {
-                let message_koto = KMap::new();
                 let message = <$enum_key>::default();
-                message_koto.insert("type", name_literal.clone());
-                message_koto.insert("category", $category_literal);
-                impl_pack!(message_koto, message);
-                Ok(KValue::Map(message_koto))
+                Ok(KotoMessage::new(message.into()).with_strict($settings.strict).into())
             } else {
                 runtime_error!($error_literal)
             }
@@ -182,19 +233,111 @@ macro_rules! make_koto_message_constructor {
     }
 }
 
+macro_rules! make_koto_text_constructor {
+    ($map:ident, $settings:ident, $type:ident, $error_literal:literal) => {
+        let name_literal = pascal_case_to_underscore_separated_literal(stringify!($type));
+        $map.add_fn(&name_literal.clone(), move |ctx| match ctx.args() {
+            [KValue::Str(text)] => {
+                Ok(KotoMessage::new($type::new(text).into()).with_strict($settings.strict).into())
+            }
+            [KValue::Map(fields)] => Ok(message_from_map(&name_literal, fields, $settings.strict)?.into()),
+            _ => runtime_error!($error_literal),
+        })
+    };
+}
 
-macro_rules! make_koto_message {
-    ($map:ident, $message:ident, $name_literal:literal,$($field:ident),*) => { 
-        $map.insert("type", $name_literal);
-        $(
-            $map.insert(stringify!($field),$message.$field());
-        )*
-        impl_pack!($map, $message);
+/// A stream parser which can be used from koto scripts.
+#[derive(Clone, Default, KotoType, KotoCopy)]
+#[koto(type_name = "Parser")]
+pub struct KotoStreamParser {
+    parser: StreamParser,
+    strict: bool,
+}
+
+#[koto_impl]
+impl KotoStreamParser {
+    #[koto_method]
+    fn feed(&mut self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let error_literal = "feed requires a single list of zero or more bytes as its argument";
+        match args {
+            [KValue::List(bytes)] => {
+                let bytes = collect_list_of_u8(bytes, error_literal)?;
+                let messages = self
+                    .parser
+                    .feed(&bytes[..])
+                    .into_iter()
+                    .map(|message| KotoMessage::new(message).with_strict(self.strict).into())
+                    .collect::<Vec<KValue>>();
+                Ok(KValue::List(KList::from_slice(&messages[..])))
+            }
+            _ => runtime_error!(error_literal),
+        }
+    }
+
+    #[koto_method]
+    fn reset(&mut self) {
+        self.parser.reset();
     }
 }
 
+impl KotoObject for KotoStreamParser {}
+
+/// Settings for the `midi` module.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModuleSettings {
+    /// Constructors and setters throw a runtime error for values which are out of the range of
+    /// their fields instead of clamping them.
+    ///
+    /// Clamping is the default since throwing in the middle of a performance is usually worse
+    /// than a wrong value.
+    pub strict: bool,
+}
+
+macro_rules! make_koto_parameter_number_constructor {
+    ($map:ident, $settings:ident, $type:ident, $name_literal:literal) => {
+        $map.add_fn($name_literal, move |ctx| {
+            let error_literal = concat!(
+                $name_literal,
+                " requires a single list of exactly three integers as its argument, or a map of its fields"
+            );
+            let (arguments, terminate) = match ctx.args() {
+                [KValue::List(arguments)] => (collect_list_of_u64(arguments, error_literal)?, false),
+                [KValue::Map(fields)] => {
+                    let fields = KMap::with_data(fields.data().clone());
+                    let terminate = match fields.data_mut().shift_remove("terminate") {
+                        Some(KValue::Bool(terminate)) => terminate,
+                        Some(_) => return runtime_error!(error_literal),
+                        None => false,
+                    };
+                    let arguments = collect_fields_of_u64(&fields, &["parameter", "value", "channel"], error_literal)?
+                        .into_iter()
+                        .map(Option::unwrap_or_default)
+                        .collect();
+                    (arguments, terminate)
+                }
+                _ => return runtime_error!(error_literal),
+            };
+            let [parameter, value, channel] = arguments[..] else {
+                return runtime_error!(error_literal);
+            };
+            let parameter_number = if $settings.strict {
+                match $type::try_new(parameter, value, channel) {
+                    Ok(parameter_number) => parameter_number,
+                    Err(error) => return runtime_error!("{}: {error}", $name_literal),
+                }
+            } else {
+                $type::new(parameter, value, channel)
+            };
+            Ok(koto_messages(parameter_number.to_messages(terminate), $settings.strict))
+        })
+    };
+}
 
 pub fn make_module() -> KMap {
+    make_module_with_settings(ModuleSettings::default())
+}
+
+pub fn make_module_with_settings(settings: ModuleSettings) -> KMap {
     let module = KMap::new();
     let types = KMap::new();
     
@@ -227,6 +370,19 @@ pub fn make_module() -> KMap {
         "stop",
         "active_sensing",
         "reset",
+        "text",
+        "copyright",
+        "track_name",
+        "instrument_name",
+        "lyric",
+        "marker",
+        "cue_point",
+        "end_of_track",
+        "tempo",
+        "smpte_offset",
+        "time_signature",
+        "key_signature",
+        "sequencer_specific",
         "undefined",
         "malformed"
     );
@@ -239,239 +395,239 @@ pub fn make_module() -> KMap {
         "channel_mode",
         "system_common",
         "system_realtime",
+        "meta",
         "unknown"
     );
 
+    let quarter_frame_pieces = KMap::new();
+    for piece in QuarterFramePiece::ALL {
+        quarter_frame_pieces.insert(piece.name(), piece.name());
+    }
+
     let message_constructors = KMap::new();
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         NoteOff,
         "channel_voice",
         note,
         velocity,
         channel,
-        "note_off requires a single list of exactly three integers as its argument"
+        "note_off requires a single list of exactly three integers as its argument, or a map of its fields"
     );
    
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         NoteOn,
         "channel_voice",
         note,
         velocity,
         channel,
-        "note_on requires a single list of exactly three integers as its argument"
+        "note_on requires a single list of exactly three integers as its argument, or a map of its fields"
     );
    
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         PolyAfterTouch,
         "channel_voice",
         note,
         pressure,
         channel,
-        "poly_after_touch requires a single list of exactly three integers as its argument"
+        "poly_after_touch requires a single list of exactly three integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         ControlChange,
         "channel_voice",
-        note,
+        controller,
         value,
         channel,
-        "control_change requires a single list of exactly three integers as its argument"
+        "control_change requires a single list of exactly three integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         ProgramChange,
         "channel_voice",
         program,
         channel,
-        "program_change requires a single list of exactly two positive integers as its argument"
+        "program_change requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         AfterTouch,
         "channel_voice",
         pressure,
         channel,
-        "after_touch requires a single list of exactly two positive integers as its argument"
+        "after_touch requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         PitchBend,
         "channel_voice",
         bend_amount,
         channel,
-        "pitch_bend requires a single list of exactly two positive integers as its argument"
+        "pitch_bend requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         AllSoundOff,
         "channel_mode",
         value,
         channel,
-        "all_sound_off requires a single list of exactly two positive integers as its argument"
+        "all_sound_off requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
    
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         ResetAllControllers,
         "channel_mode",
         value,
         channel,
-        "reset_all_controllers requires a single list of exactly two positive integers as its argument"
+        "reset_all_controllers requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         LocalControl,
         "channel_mode",
         value,
         channel,
-        "local_control requires a single list of exactly two positive integers as its argument"
+        "local_control requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         AllNotesOff,
         "channel_mode",
         value,
         channel,
-        "all_notes_off requires a single list of exactly two positive integers as its argument"
+        "all_notes_off requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         OmniModeOff,
         "channel_mode",
         value,
         channel,
-        "omni_mode_off requires a single list of exactly two positive integers as its argument"
+        "omni_mode_off requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         OmniModeOn,
         "channel_mode",
         value,
         channel,
-        "omni_mode_on requires a single list of exactly two positive integers as its argument"
+        "omni_mode_on requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         MonoModeOn,
         "channel_mode",
         value,
         channel,
-        "mono_mode_on requires a single list of exactly two positive integers as its argument"
+        "mono_mode_on requires a single list of exactly two positive integers as its argument, or a map of its fields"
     );
     
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         PolyModeOn,
         "channel_mode",
         value,
         channel,
-        "poly_mode_on requires a single list of exactly two positive integers as its argument"
-    );
-
-    // TODO: This is a very basic sysex implementation. It might be extended later. Find out if it is necessary.
-
-    message_constructors.add_fn("system_exclusive", |ctx| {
-                let error_literal = "system_exclusive requires a list with single or 3 bytes for its first argument and a list with one or more bytes for its second argument";
-                let args = ctx.args();
-                if args.len() == 2 {
-                    match args {
-                        [KValue::List(message)] => {
-                            if let Ok(arguments) = collect_list_of_value_list(message, error_literal) {
-                                if let [manufacturer_id,message] = &arguments[..] {
-                                    match manufacturer_id.len() {
-                                        1 | 3 => {
-                                            match message.len() {
-                                                0 => runtime_error!(error_literal),
-                                                _ => {                                                        
-                                                    if let Ok(m_id) = collect_list_of_u8(manufacturer_id, error_literal) {
-                                                        if let Ok(data) = collect_list_of_u8(message, error_literal) {
-                                                                let message_koto = KMap::new();
-                                                                let message = SystemExclusive::new(&m_id[..], &data[..]);
-                                                                message_koto.insert("type", "system_exclusive");
-                                                                message_koto.insert("category", "system_common");
-                                                                let m_id = m_id.iter().map(|&x| x.into()).collect::<Vec<KValue>>();
-                                                                message_koto.insert("manufacturer_id", KValue::List(KList::from_slice(&m_id[..])));
-                                                                impl_pack!(message_koto, message);
-                                                                Ok(KValue::Map(message_koto))
-                                                        }
-                                                        else{ 
-                                                            runtime_error!(error_literal)
-                                                        }
-                                                    }
-                                                    else {
-                                                        runtime_error!(error_literal)
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        _ => runtime_error!(error_literal)
-                                    }
-                                }
-                                else {
-                                runtime_error!(error_literal)
-                                }
-                            } else {
-                                Ok(KValue::Null)
-                            }
-                        }
-                        _ => runtime_error!(error_literal),
+        "poly_mode_on requires a single list of exactly two positive integers as its argument, or a map of its fields"
+    );
+
+    // Universal system exclusive messages are built by the sysex module.
+
+    message_constructors.add_fn("system_exclusive", move |ctx| {
+        let error_literal = "system_exclusive requires a list with single or 3 bytes for its first argument and a list with one or more bytes for its second argument";
+        match ctx.args() {
+            [KValue::List(message)] => {
+                let arguments = collect_list_of_value_list(message, error_literal)?;
+                match &arguments[..] {
+                    [manufacturer_id, data] if matches!(manufacturer_id.len(), 1 | 3) && !data.is_empty() => {
+                        let collect_bytes = if settings.strict {
+                            collect_list_of_midi_bytes_as_u8
+                        } else {
+                            collect_list_of_u8
+                        };
+                        let manufacturer_id = collect_bytes(manufacturer_id, error_literal)?;
+                        check_manufacturer_id(&manufacturer_id, "system_exclusive")?;
+                        let data = collect_bytes(data, error_literal)?;
+                        check_sysex_content(&data, "system_exclusive")?;
+                        let message = SystemExclusive::new(&manufacturer_id[..], &data[..]);
+                        Ok(KotoMessage::new(message.into()).with_strict(settings.strict).into())
                     }
-                } else {
-                    runtime_error!(error_literal)
+                    _ => runtime_error!(error_literal),
                 }
-            });
-
-    // TODO: Find out what are possible message types and values for time_code_quarter_frame
+            }
+            [KValue::Map(fields)] => Ok(message_from_map("system_exclusive", fields, settings.strict)?.into()),
+            _ => runtime_error!(error_literal),
+        }
+    });
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         TimeCodeQuarterFrame,
         "system_common",
-        message_type,
-        values,
-        "time_code_quarter_frame requires a single list of exactly two positive integers as its argument"
+        piece,
+        nibble,
+        "time_code_quarter_frame requires a single list of a piece number and a nibble as its argument, or a map of its fields"
     );
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         SongPosition,
         "system_common",
         midi_beats_elapsed,
-        "song_position requires a single list of exactly one positive integer as its argument"
+        "song_position requires a single list of exactly one positive integer as its argument, or a map of its fields"
     );
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         SongSelect,
         "system_common",
         number,
-        "song_select requires a single list of exactly one positive integer as its argument"
+        "song_select requires a single list of exactly one positive integer as its argument, or a map of its fields"
     );
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         TuneRequest,
         "system_common",
         "tune_request does not take any arguments"
     );
 
     make_koto_message_constructor!(
-        message_constructors,       
+        message_constructors,
+        settings,
         EndOfExclusive,
         "system_common",
         "end_of_exclusive does not take any arguments"
@@ -479,6 +635,7 @@ pub fn make_module() -> KMap {
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         TimingClock,
         "system_realtime",      
         "timing_clock does not take any arguments"
@@ -486,6 +643,7 @@ pub fn make_module() -> KMap {
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         Start,
         "system_realtime", 
         "start does not take any arguments"
@@ -493,6 +651,7 @@ pub fn make_module() -> KMap {
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         Continue,
         "system_realtime",
         "continue does not take any arguments"
@@ -500,6 +659,7 @@ pub fn make_module() -> KMap {
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         Stop,
         "system_realtime",
         "stop does not take any arguments"
@@ -507,6 +667,7 @@ pub fn make_module() -> KMap {
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         ActiveSensing,
         "system_realtime",
         "active_sensing does not take any arguments"
@@ -514,181 +675,199 @@ pub fn make_module() -> KMap {
 
     make_koto_message_constructor!(
         message_constructors,
+        settings,
         Reset,
         "system_realtime",
         "reset does not take any arguments"
     );
 
-    module.add_fn("parse", |ctx| {
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        Text,
+        "text requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        Copyright,
+        "copyright requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        TrackName,
+        "track_name requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        InstrumentName,
+        "instrument_name requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        Lyric,
+        "lyric requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        Marker,
+        "marker requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_text_constructor!(
+        message_constructors,
+        settings,
+        CuePoint,
+        "cue_point requires a single string as its argument, or a map of its fields"
+    );
+
+    make_koto_message_constructor!(
+        message_constructors,
+        settings,
+        EndOfTrack,
+        "meta",
+        "end_of_track does not take any arguments"
+    );
+
+    make_koto_message_constructor!(
+        message_constructors,
+        settings,
+        Tempo,
+        "meta",
+        microseconds_per_quarter_note,
+        "tempo requires a single list of exactly one positive integer as its argument, or a map of its fields"
+    );
+
+    make_koto_message_constructor!(
+        message_constructors,
+        settings,
+        SmpteOffset,
+        "meta",
+        hours,
+        minutes,
+        seconds,
+        frames,
+        fractional_frames,
+        rate,
+        "smpte_offset requires a single list of exactly six positive integers as its argument, or a map of its fields"
+    );
+
+    make_koto_message_constructor!(
+        message_constructors,
+        settings,
+        TimeSignature,
+        "meta",
+        numerator,
+        denominator,
+        clocks_per_click,
+        thirty_seconds_per_quarter_note,
+        "time_signature requires a single list of exactly four positive integers as its argument, or a map of its fields"
+    );
+
+    make_koto_message_constructor!(
+        message_constructors,
+        settings,
+        KeySignature,
+        "meta",
+        sharps,
+        flats,
+        minor,
+        "key_signature requires a single list of exactly three positive integers as its argument, or a map of its fields"
+    );
+
+    message_constructors.add_fn("sequencer_specific", move |ctx| {
+        let error_literal = "sequencer_specific requires a list with single or 3 bytes for its first argument and a list of bytes for its second argument";
+        match ctx.args() {
+            [KValue::List(message)] => {
+                let arguments = collect_list_of_value_list(message, error_literal)?;
+                match &arguments[..] {
+                    [manufacturer_id, data] if matches!(manufacturer_id.len(), 1 | 3) => {
+                        let manufacturer_id = collect_list_of_u8(manufacturer_id, error_literal)?;
+                        check_manufacturer_id(&manufacturer_id, "sequencer_specific")?;
+                        let data = collect_list_of_u8(data, error_literal)?;
+                        let message = SequencerSpecific::new(&manufacturer_id[..], &data[..]);
+                        Ok(KotoMessage::new(message.into()).with_strict(settings.strict).into())
+                    }
+                    _ => runtime_error!(error_literal),
+                }
+            }
+            [KValue::Map(fields)] => Ok(message_from_map("sequencer_specific", fields, settings.strict)?.into()),
+            _ => runtime_error!(error_literal),
+        }
+    });
+
+    message_constructors.add_fn("control_change_14", move |ctx| {
+        let error_literal = "control_change_14 requires a single list of exactly three integers as its argument, or a map of its fields";
+        let arguments = match ctx.args() {
+            [KValue::List(arguments)] => collect_list_of_u64(arguments, error_literal)?,
+            [KValue::Map(fields)] => {
+                collect_fields_of_u64(fields, &["controller", "value", "channel"], error_literal)?
+                    .into_iter()
+                    .map(Option::unwrap_or_default)
+                    .collect()
+            }
+            _ => return runtime_error!(error_literal),
+        };
+        let [controller, value, channel] = arguments[..] else {
+            return runtime_error!(error_literal);
+        };
+        let controller = if settings.strict {
+            match ControlChange14::try_new(controller, value, channel) {
+                Ok(controller) => controller,
+                Err(error) => return runtime_error!("control_change_14: {error}"),
+            }
+        } else {
+            ControlChange14::new(controller, value, channel)
+        };
+        Ok(koto_messages(controller.to_messages(), settings.strict))
+    });
+
+    make_koto_parameter_number_constructor!(message_constructors, settings, Rpn, "rpn");
+    make_koto_parameter_number_constructor!(message_constructors, settings, Nrpn, "nrpn");
+
+    message_constructors.add_fn("from_map", move |ctx| {
+        let error_literal = "from_map requires a single map with a type and the fields of a message as its argument";
+        match ctx.args() {
+            [KValue::Map(fields)] => match fields.get("type") {
+                Some(KValue::Str(type_literal)) => Ok(message_from_map(&type_literal, fields, settings.strict)?.into()),
+                _ => runtime_error!(error_literal),
+            },
+            _ => runtime_error!(error_literal),
+        }
+    });
+
+    module.add_fn("parse", move |ctx| {
         let args = ctx.args();
         if args.len() == 1 {
             match args {
                 [KValue::List(message)] => {
-                    let message_koto = KMap::new();
                     if let Ok(midi_message) = collect_list_of_u8(
                         message,
                         "parse requires a single list of one or more positive integers as its argument",
                     ) {
                         let parsed = ParsedMessage::from(&midi_message[..]);
-                        let message = parsed.message;
-
-                        match message {
-                            Message::NoteOn(_)
-                            | Message::NoteOff(_)
-                            | Message::ControlChange(_)
-                            | Message::ProgramChange(_)
-                            | Message::PitchBend(_)
-                            | Message::AfterTouch(_)
-                            | Message::PolyAfterTouch(_) => {
-                                message_koto.insert("category", "channel_voice")
-                            }
-                            Message::AllSoundOff(_)
-                            | Message::ResetAllControllers(_)
-                            | Message::LocalControl(_)
-                            | Message::AllNotesOff(_)
-                            | Message::OmniModeOff(_)
-                            | Message::OmniModeOn(_)
-                            | Message::MonoModeOn(_)
-                            | Message::PolyModeOn(_) => {
-                                message_koto.insert("category", "channel_mode")
-                            }
-                            Message::SystemExclusive(_)
-                            | Message::SongPosition(_)
-                            | Message::SongSelect(_)
-                            | Message::TuneRequest(_)
-                            | Message::EndOfExclusive(_)
-                            | Message::TimeCodeQuarterFrame(_) => {
-                                message_koto.insert("category", "system_common")
-                            }
-                            Message::TimingClock(_)
-                            | Message::Start(_)
-                            | Message::Continue(_)
-                            | Message::Stop(_)
-                            | Message::ActiveSensing(_)
-                            | Message::Reset(_) => {
-                                message_koto.insert("category", "system_realtime")
-                            }
-                            Message::Undefined | Message::Malformed => {
-                                message_koto.insert("category", "unknown")
-                            }
-                        };
-
-                        match message {
-                            Message::NoteOff(message) => {
-                                make_koto_message!(message_koto, message, "note_off", note, velocity, channel);
-                            }
-                            Message::NoteOn(message) => {
-                                make_koto_message!(message_koto, message, "note_on", note, velocity, channel);
-                            }
-                            Message::ControlChange(message) => {
-                                make_koto_message!(message_koto, message, "control_change", note, value, channel);
-                            }
-                            Message::ProgramChange(message) => {
-                                make_koto_message!(message_koto, message, "program_change", program, channel);
-                            }
-
-                            Message::AfterTouch(message) => {
-                                make_koto_message!(message_koto, message, "after_touch", pressure, channel);
-                            }
-                            Message::PolyAfterTouch(message) => {
-                                make_koto_message!(message_koto, message, "poly_after_touch", note, pressure, channel);
-                            }
-                            Message::PitchBend(message) => {
-                                make_koto_message!(message_koto, message, "pitch_bend", bend_amount, channel);
-                            }
-                            Message::AllSoundOff(message) => {
-                                make_koto_message!(message_koto, message, "all_sound_off", value, channel);
-                                message_koto.insert("note", 120);
-                            }
-                            Message::ResetAllControllers(message) => {
-                                make_koto_message!(message_koto, message, "reset_all_controllers", value, channel);
-                                message_koto.insert("note", 121);
-                            }
-                            Message::LocalControl(message) => {
-                                make_koto_message!(message_koto, message, "local_control", value, channel);
-                                message_koto.insert("note", 122);
-                            }
-                            Message::AllNotesOff(message) => {
-                                make_koto_message!(message_koto, message, "all_notes_off", value, channel);
-                                message_koto.insert("note", 123);
-                            }
-                            Message::OmniModeOff(message) => {
-                                make_koto_message!(message_koto, message, "omni_mode_off", value, channel);
-                                message_koto.insert("note", 124);
-                            }
-                            Message::OmniModeOn(message) => {
-                                make_koto_message!(message_koto, message, "omni_mode_on", value, channel);
-                                message_koto.insert("note", 125);
-                            }
-                            Message::MonoModeOn(message) => {
-                                make_koto_message!(message_koto, message, "mono_mode_on", value, channel);
-                                message_koto.insert("note", 126);
-                            }
-                            Message::PolyModeOn(message) => {
-                                make_koto_message!(message_koto, message, "poly_mode_on", value, channel);
-                                message_koto.insert("note", 127);
-                            }
-                            Message::SystemExclusive(message) => {
-                                message_koto.insert("type", "system_exclusive");
-                                let m_id = message.manufacturer_id.iter().map(|&x| x.into()).collect::<Vec<KValue>>();
-                                message_koto.insert("manufacturer_id", KValue::List(KList::from_slice(&m_id[..])));
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::SongPosition(message) => {
-                                make_koto_message!(message_koto, message, "song_position", midi_beats_elapsed);
-                            }
-                            Message::SongSelect(message) => {
-                                make_koto_message!(message_koto, message, "song_select", number);
-                            }
-                            Message::TuneRequest(message) => {
-                                message_koto.insert("type", "tune_request");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::EndOfExclusive(message) => {
-                                message_koto.insert("type", "end_of_exclusive");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::TimeCodeQuarterFrame(message) => {
-                                make_koto_message!(message_koto, message, "time_code_quarter_frame", message_type, values);
-                            }
-                            Message::TimingClock(message) => {
-                                message_koto.insert("type", "timing_clock");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::Start(message) => {
-                                message_koto.insert("type", "start");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::Continue(message) => {
-                                message_koto.insert("type", "continue");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::Stop(message) => {
-                                message_koto.insert("type", "stop");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::ActiveSensing(message) => {
-                                message_koto.insert("type", "active_sensing");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::Reset(message) => {
-                                message_koto.insert("type", "reset");
-                                impl_pack!(message_koto, message);
-                            }
-                            Message::Undefined => {
-                                message_koto.insert("type", "undefined");
-                            }
-                            Message::Malformed => {
-                                message_koto.insert("type", "malformed");
-                            }
-                        }
-
-                        Ok(KValue::Map(message_koto))
+                        let realtime_before_message = parsed.realtime_before_message();
+                        Ok(KotoMessage::new(parsed.message)
+                            .with_realtime(parsed.realtime, realtime_before_message)
+                            .with_strict(settings.strict)
+                            .into())
                     } else {
-                        message_koto.insert("type", "malformed");
-                        message_koto.insert("category", "unknown");
-                        // Returns an empty value if the message is malformed.
-                        Ok(KValue::Map(message_koto))
+                        let offset = message.data().iter().position(|value| {
+                            !matches!(value, KValue::Number(KNumber::I64(byte)) if (0..=255).contains(byte))
+                        });
+                        let error = ParseError::InvalidByte {
+                            offset: offset.unwrap_or_default(),
+                        };
+                        Ok(KotoMessage::new(Message::Malformed(error)).into())
                     }
                 }
                 _ => runtime_error!(
@@ -700,24 +879,87 @@ pub fn make_module() -> KMap {
         }
     });
 
+    module.add_fn("parse_all", move |ctx| {
+        let error_literal = "parse_all requires a single list of zero or more bytes as its argument";
+        match ctx.args() {
+            [KValue::List(bytes)] => {
+                let bytes = collect_list_of_u8(bytes, error_literal)?;
+                let messages = parse_all(&bytes[..])
+                    .into_iter()
+                    .map(|(offset, message)| {
+                        KotoMessage::new(message)
+                            .with_offset(offset)
+                            .with_strict(settings.strict)
+                            .into()
+                    })
+                    .collect::<Vec<KValue>>();
+                Ok(KValue::List(KList::from_slice(&messages[..])))
+            }
+            _ => runtime_error!(error_literal),
+        }
+    });
+
+    module.add_fn("parser", move |ctx| {
+        if ctx.args().is_empty() {
+            let parser = KotoStreamParser {
+                parser: StreamParser::new(),
+                strict: settings.strict,
+            };
+            Ok(KValue::Object(parser.into()))
+        } else {
+            runtime_error!("parser does not take any arguments")
+        }
+    });
+
+    module.add_fn("parameter_number_decoder", |ctx| {
+        if ctx.args().is_empty() {
+            Ok(KValue::Object(KotoParameterNumberDecoder::default().into()))
+        } else {
+            runtime_error!("parameter_number_decoder does not take any arguments")
+        }
+    });
+
+    module.add_fn("control_change_14_decoder", |ctx| {
+        if ctx.args().is_empty() {
+            Ok(KValue::Object(KotoControlChange14Decoder::default().into()))
+        } else {
+            runtime_error!("control_change_14_decoder does not take any arguments")
+        }
+    });
+
+    module.add_fn("tempo_map", move |ctx| {
+        let error_literal =
+            "tempo_map requires no arguments, ticks per quarter note or a song map as its argument";
+        let map = match ctx.args() {
+            [] => TempoMap::default(),
+            [KValue::Number(KNumber::I64(ticks_per_quarter_note))]
+                if (1..=0x7FFF).contains(ticks_per_quarter_note) =>
+            {
+                TempoMap::new(*ticks_per_quarter_note as u16)
+            }
+            [KValue::Map(song)] => {
+                let (file, _) = file_module::song_from_map(song, settings.strict, "midi.tempo_map")?;
+                match TempoMap::from_file(&file) {
+                    Some(map) => map,
+                    None => return runtime_error!("midi.tempo_map: songs with smpte timing have no tempo map"),
+                }
+            }
+            _ => return runtime_error!(error_literal),
+        };
+        Ok(KValue::Object(KotoTempoMap::new(map).with_strict(settings.strict).into()))
+    });
+
     module.insert("types", types);
     module.insert("categories", categories);
+    module.insert("quarter_frame_pieces", quarter_frame_pieces);
     module.insert("message", message_constructors);
+    module.insert("file", file_module::make_file_module(settings));
+    module.insert("mmc", mmc_module::make_mmc_module(settings));
+    module.insert("mtc", mtc_module::make_mtc_module(settings));
+    module.insert("mts", mts_module::make_mts_module(settings));
+    module.insert("roland", vendor_module::make_roland_module(settings));
+    module.insert("scala", scala_module::make_scala_module());
+    module.insert("sysex", sysex_module::make_sysex_module(settings));
+    module.insert("yamaha", vendor_module::make_yamaha_module(settings));
     module
 }
-
-pub trait MidiMessage {
-    fn pack(&self) -> &[u8];
-}
-
-macro_rules! impl_midi_message {
-    ($type:ty) => {
-        impl MidiMessage for $type {
-            fn pack(&self) -> &[u8] {
-                &self.bytes
-            }
-        }
-    };
-}
-
-pub(crate) use impl_midi_message;
@@ -0,0 +1,177 @@
+//! A registry of the manufacturer ids which start system exclusive messages.
+//!
+//! Ids are a single byte, or 3 bytes which start with 0 for the extended ids. The range of an id
+//! tells the region of its manufacturer, and the ids 0x7D, 0x7E and 0x7F are reserved for
+//! non-commercial use and the universal messages.
+
+pub const NON_COMMERCIAL: u8 = 0x7D;
+
+/// The region which a manufacturer id was assigned for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    NorthAmerica,
+    Europe,
+    Japan,
+    Other,
+    /// The reserved ids for non-commercial use and universal messages.
+    Special,
+}
+
+impl Region {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::NorthAmerica => "north_america",
+            Region::Europe => "europe",
+            Region::Japan => "japan",
+            Region::Other => "other",
+            Region::Special => "special",
+        }
+    }
+}
+
+/// A known manufacturer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Manufacturer {
+    pub id: &'static [u8],
+    pub name: &'static str,
+}
+
+const fn manufacturer(id: &'static [u8], name: &'static str) -> Manufacturer {
+    Manufacturer { id, name }
+}
+
+/// The manufacturers which the library knows, single byte ids first.
+pub const MANUFACTURERS: &[Manufacturer] = &[
+    manufacturer(&[0x01], "Sequential"),
+    manufacturer(&[0x04], "Moog"),
+    manufacturer(&[0x06], "Lexicon"),
+    manufacturer(&[0x07], "Kurzweil"),
+    manufacturer(&[0x0F], "Ensoniq"),
+    manufacturer(&[0x10], "Oberheim"),
+    manufacturer(&[0x11], "Apple"),
+    manufacturer(&[0x18], "E-mu"),
+    manufacturer(&[0x1C], "Eventide"),
+    manufacturer(&[0x24], "Hohner"),
+    manufacturer(&[0x2B], "Solid State Logic"),
+    manufacturer(&[0x30], "Dynacord"),
+    manufacturer(&[0x33], "Clavia"),
+    manufacturer(&[0x3A], "Steinberg"),
+    manufacturer(&[0x3E], "Waldorf"),
+    manufacturer(&[0x40], "Kawai"),
+    manufacturer(&[0x41], "Roland"),
+    manufacturer(&[0x42], "Korg"),
+    manufacturer(&[0x43], "Yamaha"),
+    manufacturer(&[0x44], "Casio"),
+    manufacturer(&[0x47], "Akai"),
+    manufacturer(&[0x48], "Victor"),
+    manufacturer(&[0x4B], "Fujitsu"),
+    manufacturer(&[0x4C], "Sony"),
+    manufacturer(&[0x4E], "Teac"),
+    manufacturer(&[0x50], "Matsushita"),
+    manufacturer(&[0x51], "Fostex"),
+    manufacturer(&[0x52], "Zoom"),
+    manufacturer(&[0x7D], "Non-commercial"),
+    manufacturer(&[0x7E], "Universal non-realtime"),
+    manufacturer(&[0x7F], "Universal realtime"),
+    manufacturer(&[0x00, 0x00, 0x0E], "Alesis"),
+    manufacturer(&[0x00, 0x00, 0x3B], "MOTU"),
+    manufacturer(&[0x00, 0x01, 0x05], "M-Audio"),
+    manufacturer(&[0x00, 0x20, 0x29], "Focusrite/Novation"),
+    manufacturer(&[0x00, 0x20, 0x32], "Behringer"),
+    manufacturer(&[0x00, 0x20, 0x33], "Access"),
+    manufacturer(&[0x00, 0x20, 0x3C], "Elektron"),
+    manufacturer(&[0x00, 0x20, 0x6B], "Arturia"),
+    manufacturer(&[0x00, 0x21, 0x09], "Native Instruments"),
+];
+
+/// The reason a manufacturer id is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManufacturerIdError {
+    /// Ids have a single or 3 bytes.
+    InvalidLength {
+        length: usize,
+    },
+    /// 0 on its own starts an extended id, 3 byte ids must start with it.
+    InvalidExtendedId,
+    DataByteOutOfRange {
+        byte: u8,
+    },
+}
+
+impl std::fmt::Display for ManufacturerIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManufacturerIdError::InvalidLength { length } => {
+                write!(
+                    f,
+                    "a manufacturer id has a single or 3 bytes, found {length}"
+                )
+            }
+            ManufacturerIdError::InvalidExtendedId => {
+                write!(
+                    f,
+                    "extended manufacturer ids are 3 bytes which start with 0"
+                )
+            }
+            ManufacturerIdError::DataByteOutOfRange { byte } => {
+                write!(f, "manufacturer id byte {byte:#04X} is greater than 0x7F")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManufacturerIdError {}
+
+/// The length of the id at the start of the given data, 3 when it starts with 0.
+///
+/// Data which is too short for an extended id gives the length of the data.
+pub fn id_length(data: &[u8]) -> usize {
+    match data.first() {
+        Some(0) => 3.min(data.len()),
+        Some(_) => 1,
+        None => 0,
+    }
+}
+
+/// Checks the length of an id and that extended ids start with 0, then that its bytes are data
+/// bytes.
+pub fn validate(id: &[u8]) -> Result<(), ManufacturerIdError> {
+    match id {
+        [0] => return Err(ManufacturerIdError::InvalidExtendedId),
+        [first, _, _] if *first != 0 => return Err(ManufacturerIdError::InvalidExtendedId),
+        [_] | [_, _, _] => {}
+        _ => return Err(ManufacturerIdError::InvalidLength { length: id.len() }),
+    }
+    match id.iter().find(|&&byte| byte > 0x7F) {
+        Some(&byte) => Err(ManufacturerIdError::DataByteOutOfRange { byte }),
+        None => Ok(()),
+    }
+}
+
+/// The region of a valid id, `None` for invalid ones.
+pub fn region(id: &[u8]) -> Option<Region> {
+    validate(id).ok()?;
+    let group = match id {
+        [NON_COMMERCIAL..=0x7F] => return Some(Region::Special),
+        [id] => *id,
+        [_, group, _] => *group,
+        _ => return None,
+    };
+    Some(match group {
+        0x00..=0x1F => Region::NorthAmerica,
+        0x20..=0x3F => Region::Europe,
+        0x40..=0x5F => Region::Japan,
+        _ => Region::Other,
+    })
+}
+
+pub fn lookup(id: &[u8]) -> Option<&'static Manufacturer> {
+    MANUFACTURERS
+        .iter()
+        .find(|manufacturer| manufacturer.id == id)
+}
+
+/// The name of a known manufacturer.
+pub fn name(id: &[u8]) -> Option<&'static str> {
+    lookup(id).map(|manufacturer| manufacturer.name)
+}
@@ -1,24 +1,65 @@
-#![allow(unused)]
+//! Midi message types and parsers.
+//!
+//! Messages can be built with the constructors of each message type, or parsed from raw bytes
+//! with [Message::parse], [ParsedMessage], [parse_all] or a [StreamParser].
+//!
+//! The `new` constructors and `change_` setters clamp values to the range of their fields,
+//! the `try_new` constructors return a [RangeError] instead, or a [SystemExclusiveError] for
+//! system exclusive messages which also check their manufacturer id.
+//!
+//! Meta events are the events of standard midi files which aren't sent to devices. They start
+//! with `0xFF` like a reset message, followed by their type, the length of their data as
+//! a variable length quantity and the data. Parsers which read a live stream treat `0xFF` as
+//! a reset message.
 
 mod channel_mode;
 mod channel_voice;
+mod meta;
 mod system_common;
 mod system_realtime;
 pub use channel_mode::*;
 pub use channel_voice::*;
+pub use meta::*;
 pub use system_common::*;
 pub use system_realtime::*;
 
-#[derive(Debug)]
+/// Access to the raw bytes of a message.
+pub trait MidiMessage {
+    fn pack(&self) -> &[u8];
+}
+
+macro_rules! impl_midi_message {
+    ($type:ident) => {
+        impl MidiMessage for $type {
+            fn pack(&self) -> &[u8] {
+                &self.bytes
+            }
+        }
+
+        impl From<$type> for $crate::message::Message {
+            fn from(message: $type) -> Self {
+                $crate::message::Message::$type(message)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_midi_message;
+
+/// The category a message belongs to in the midi specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Category {
     ChannelVoice,
     ChannelMode,
     SystemCommon,
     SystemRealtime,
+    /// Meta events of standard midi files.
+    Meta,
     Unknown,
 }
 
-#[derive(Debug)]
+/// Any midi message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Message {
     NoteOn(NoteOn),
     NoteOff(NoteOff),
@@ -38,8 +79,10 @@ pub enum Message {
     Stop(Stop),
     ActiveSensing(ActiveSensing),
     Reset(Reset),
+    /// A message with a status byte which is reserved by the midi specification.
     Undefined,
-    Malformed,
+    /// Bytes which couldn't be parsed as a message.
+    Malformed(ParseError),
     EndOfExclusive(EndOfExclusive),
     AllSoundOff(AllSoundOff),
     ResetAllControllers(ResetAllControllers),
@@ -49,128 +92,623 @@ pub enum Message {
     OmniModeOn(OmniModeOn),
     MonoModeOn(MonoModeOn),
     PolyModeOn(PolyModeOn),
+    Text(Text),
+    Copyright(Copyright),
+    TrackName(TrackName),
+    InstrumentName(InstrumentName),
+    Lyric(Lyric),
+    Marker(Marker),
+    CuePoint(CuePoint),
+    EndOfTrack(EndOfTrack),
+    Tempo(Tempo),
+    SmpteOffset(SmpteOffset),
+    TimeSignature(TimeSignature),
+    KeySignature(KeySignature),
+    SequencerSpecific(SequencerSpecific),
 }
 
-#[derive(Debug)]
-pub struct ParsedMessage {
-    pub message: Message,
+/// Evaluates an expression with the inner message of every variant which has one.
+macro_rules! with_inner_message {
+    ($message:expr, $inner:ident => $expression:expr, $otherwise:expr) => {
+        match $message {
+            Message::NoteOn($inner) => $expression,
+            Message::NoteOff($inner) => $expression,
+            Message::ControlChange($inner) => $expression,
+            Message::ProgramChange($inner) => $expression,
+            Message::PitchBend($inner) => $expression,
+            Message::AfterTouch($inner) => $expression,
+            Message::PolyAfterTouch($inner) => $expression,
+            Message::SystemExclusive($inner) => $expression,
+            Message::SongPosition($inner) => $expression,
+            Message::SongSelect($inner) => $expression,
+            Message::TuneRequest($inner) => $expression,
+            Message::TimeCodeQuarterFrame($inner) => $expression,
+            Message::TimingClock($inner) => $expression,
+            Message::Start($inner) => $expression,
+            Message::Continue($inner) => $expression,
+            Message::Stop($inner) => $expression,
+            Message::ActiveSensing($inner) => $expression,
+            Message::Reset($inner) => $expression,
+            Message::EndOfExclusive($inner) => $expression,
+            Message::AllSoundOff($inner) => $expression,
+            Message::ResetAllControllers($inner) => $expression,
+            Message::LocalControl($inner) => $expression,
+            Message::AllNotesOff($inner) => $expression,
+            Message::OmniModeOff($inner) => $expression,
+            Message::OmniModeOn($inner) => $expression,
+            Message::MonoModeOn($inner) => $expression,
+            Message::PolyModeOn($inner) => $expression,
+            Message::Text($inner) => $expression,
+            Message::Copyright($inner) => $expression,
+            Message::TrackName($inner) => $expression,
+            Message::InstrumentName($inner) => $expression,
+            Message::Lyric($inner) => $expression,
+            Message::Marker($inner) => $expression,
+            Message::CuePoint($inner) => $expression,
+            Message::EndOfTrack($inner) => $expression,
+            Message::Tempo($inner) => $expression,
+            Message::SmpteOffset($inner) => $expression,
+            Message::TimeSignature($inner) => $expression,
+            Message::KeySignature($inner) => $expression,
+            Message::SequencerSpecific($inner) => $expression,
+            Message::Undefined | Message::Malformed(_) => $otherwise,
+        }
+    };
 }
 
-impl From<&[u8]> for ParsedMessage {
-    fn from(raw_message: &[u8]) -> Self {
-        let status_byte = raw_message[0];
-        let data_bytes = raw_message[1..].to_vec();
-        let data_bytes_length = data_bytes.len();
-        let message = match status_byte & 0xF0 {
-            0x80 => match data_bytes_length {
-                2 if data_bytes[0] <= 127 && data_bytes[1] <= 127 => {
-                    Message::NoteOff(raw_message.into())
-                }
-                _ => Message::Malformed,
-            },
+impl Message {
+    /// Parses a single message.
+    ///
+    /// System realtime bytes which are interleaved with the message are skipped,
+    /// use [ParsedMessage] to keep them.
+    pub fn parse(bytes: &[u8]) -> Result<Message, ParseError> {
+        match ParsedMessage::from(bytes).message {
+            Message::Malformed(error) => Err(error),
+            Message::Undefined => Err(ParseError::UndefinedStatus { status: bytes[0] }),
+            message => Ok(message),
+        }
+    }
 
-            0x90 => match data_bytes_length {
-                2 if data_bytes[0] <= 127 && data_bytes[1] <= 127 => {
-                    Message::NoteOn(raw_message.into())
-                }
-                _ => Message::Malformed,
+    /// The raw bytes of the message, undefined and malformed messages don't have any.
+    pub fn pack(&self) -> &[u8] {
+        with_inner_message!(self, message => message.pack(), &[])
+    }
+
+    /// Returns a copy of the raw bytes of the message.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pack().to_vec()
+    }
+
+    pub fn category(&self) -> Category {
+        with_inner_message!(self, message => message.category, Category::Unknown)
+    }
+
+    pub fn is_channel_voice(&self) -> bool {
+        self.category() == Category::ChannelVoice
+    }
+
+    pub fn is_channel_mode(&self) -> bool {
+        self.category() == Category::ChannelMode
+    }
+
+    pub fn is_system_common(&self) -> bool {
+        self.category() == Category::SystemCommon
+    }
+
+    pub fn is_system_realtime(&self) -> bool {
+        self.category() == Category::SystemRealtime
+    }
+
+    pub fn is_meta(&self) -> bool {
+        self.category() == Category::Meta
+    }
+
+    pub fn is_malformed(&self) -> bool {
+        matches!(self, Message::Malformed(_))
+    }
+}
+
+/// The reason a message could not be parsed.
+///
+/// Offsets point to the byte in the parsed input which caused the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseError {
+    /// The input doesn't contain any bytes.
+    Empty,
+    /// A data byte was found where a status byte was expected.
+    UnexpectedDataByte { offset: usize },
+    /// The number of data bytes doesn't match what the status byte requires.
+    ///
+    /// For system exclusive messages `expected` is the minimum number of bytes.
+    InvalidLength {
+        status: u8,
+        expected: usize,
+        found: usize,
+    },
+    /// A system exclusive message doesn't end with an end of exclusive byte.
+    MissingEndOfExclusive { offset: usize },
+    /// A data byte is above 127.
+    DataByteOutOfRange { offset: usize, byte: u8 },
+    /// The status byte is reserved and undefined by the midi specification.
+    UndefinedStatus { status: u8 },
+    /// The status byte doesn't belong to the type of message being parsed.
+    UnexpectedStatus { status: u8 },
+    /// The controller number doesn't belong to the channel mode message being parsed.
+    UnexpectedController { controller: u8 },
+    /// A value in the input isn't a byte.
+    ///
+    /// This can only happen when the input is collected from a list of arbitrary values.
+    InvalidByte { offset: usize },
+    /// The type of a meta event is unknown or doesn't belong to the meta event being parsed.
+    UnexpectedMetaType { meta_type: u8 },
+    /// The data of a meta event has the wrong length or a value which is out of range.
+    InvalidMetaData { meta_type: u8 },
+}
+
+impl ParseError {
+    /// A short snake case name for the error.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ParseError::Empty => "empty",
+            ParseError::UnexpectedDataByte { .. } => "unexpected_data_byte",
+            ParseError::InvalidLength { .. } => "invalid_length",
+            ParseError::MissingEndOfExclusive { .. } => "missing_end_of_exclusive",
+            ParseError::DataByteOutOfRange { .. } => "data_byte_out_of_range",
+            ParseError::UndefinedStatus { .. } => "undefined_status",
+            ParseError::UnexpectedStatus { .. } => "unexpected_status",
+            ParseError::UnexpectedController { .. } => "unexpected_controller",
+            ParseError::InvalidByte { .. } => "invalid_byte",
+            ParseError::UnexpectedMetaType { .. } => "unexpected_meta_type",
+            ParseError::InvalidMetaData { .. } => "invalid_meta_data",
+        }
+    }
+
+    /// The offset of the byte which caused the error.
+    pub fn offset(&self) -> usize {
+        match *self {
+            ParseError::Empty
+            | ParseError::UndefinedStatus { .. }
+            | ParseError::UnexpectedStatus { .. } => 0,
+            ParseError::UnexpectedController { .. }
+            | ParseError::UnexpectedMetaType { .. }
+            | ParseError::InvalidMetaData { .. } => 1,
+            ParseError::InvalidLength {
+                expected, found, ..
+            } => 1 + expected.min(found),
+            ParseError::UnexpectedDataByte { offset }
+            | ParseError::MissingEndOfExclusive { offset }
+            | ParseError::DataByteOutOfRange { offset, .. }
+            | ParseError::InvalidByte { offset } => offset,
+        }
+    }
+
+    fn map_offset(self, f: impl Fn(usize) -> usize) -> Self {
+        match self {
+            ParseError::UnexpectedDataByte { offset } => ParseError::UnexpectedDataByte {
+                offset: f(offset),
             },
-            0xA0 => match data_bytes_length {
-                2 if data_bytes[0] <= 127 && data_bytes[1] <= 127 => {
-                    Message::PolyAfterTouch(raw_message.into())
-                }
-                _ => Message::Malformed,
+            ParseError::MissingEndOfExclusive { offset } => ParseError::MissingEndOfExclusive {
+                offset: f(offset),
             },
-            0xB0 => match data_bytes_length {
-                2 if data_bytes[0] <= 127 && data_bytes[1] <= 127 => {
-                    match data_bytes[0] & 0b0111_1111 {
-                        0..120 => Message::ControlChange(raw_message.into()),
-                        120 => Message::AllSoundOff(raw_message.into()),
-                        121 => Message::ResetAllControllers(raw_message.into()),
-                        122 => Message::LocalControl(raw_message.into()),
-                        123 => Message::AllNotesOff(raw_message.into()),
-                        124 => Message::OmniModeOff(raw_message.into()),
-                        125 => Message::OmniModeOn(raw_message.into()),
-                        126 => Message::MonoModeOn(raw_message.into()),
-                        127 => Message::PolyModeOn(raw_message.into()),
-                        _ => Message::Malformed,
-                    }
-                }
-                _ => Message::Malformed,
+            ParseError::DataByteOutOfRange { offset, byte } => ParseError::DataByteOutOfRange {
+                offset: f(offset),
+                byte,
             },
+            error => error,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the message is empty"),
+            ParseError::UnexpectedDataByte { offset } => {
+                write!(f, "expected a status byte at offset {offset}, found a data byte")
+            }
+            ParseError::InvalidLength {
+                status,
+                expected,
+                found,
+            } => write!(
+                f,
+                "status byte {status:#04X} requires {expected} data bytes, found {found}"
+            ),
+            ParseError::MissingEndOfExclusive { offset } => {
+                write!(f, "expected end of exclusive at offset {offset}")
+            }
+            ParseError::DataByteOutOfRange { offset, byte } => {
+                write!(f, "data byte {byte:#04X} at offset {offset} is above 127")
+            }
+            ParseError::UndefinedStatus { status } => {
+                write!(f, "status byte {status:#04X} is undefined")
+            }
+            ParseError::UnexpectedStatus { status } => {
+                write!(f, "unexpected status byte {status:#04X}")
+            }
+            ParseError::UnexpectedController { controller } => {
+                write!(f, "unexpected controller number {controller}")
+            }
+            ParseError::InvalidByte { offset } => {
+                write!(f, "the value at offset {offset} is not a byte")
+            }
+            ParseError::UnexpectedMetaType { meta_type } => {
+                write!(f, "unexpected meta event type {meta_type:#04X}")
+            }
+            ParseError::InvalidMetaData { meta_type } => {
+                write!(f, "the data of meta event type {meta_type:#04X} is invalid")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A value which doesn't fit in the message field it was given for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RangeError {
+    pub field: &'static str,
+    pub value: u64,
+    pub max: u64,
+    /// The field only takes powers of 2, like the denominator of a time signature.
+    pub power_of_two: bool,
+}
+
+impl std::fmt::Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.power_of_two {
+            write!(
+                f,
+                "{} must be a power of 2 between 1 and {}, found {}",
+                self.field, self.max, self.value
+            )
+        } else {
+            write!(
+                f,
+                "{} must be between 0 and {}, found {}",
+                self.field, self.max, self.value
+            )
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+pub(crate) fn check_range(field: &'static str, value: u64, max: u64) -> Result<u64, RangeError> {
+    if value > max {
+        Err(RangeError {
+            field,
+            value,
+            max,
+            power_of_two: false,
+        })
+    } else {
+        Ok(value)
+    }
+}
+
+pub(crate) fn check_power_of_two(
+    field: &'static str,
+    value: u64,
+    max: u64,
+) -> Result<u64, RangeError> {
+    if value > max || !value.is_power_of_two() {
+        Err(RangeError {
+            field,
+            value,
+            max,
+            power_of_two: true,
+        })
+    } else {
+        Ok(value)
+    }
+}
+
+/// Checks the status byte of a message.
+///
+/// The channel bits are ignored when `status` is a channel message status.
+pub(crate) fn check_status(raw_bytes: &[u8], status: u8) -> Result<(), ParseError> {
+    let status_byte = *raw_bytes.first().ok_or(ParseError::Empty)?;
+    let matches = if status < 0xF0 {
+        status_byte & 0xF0 == status
+    } else {
+        status_byte == status
+    };
+    match status_byte {
+        0x00..=0x7F => Err(ParseError::UnexpectedDataByte { offset: 0 }),
+        0xF4 | 0xF5 | 0xF9 | 0xFD => Err(ParseError::UndefinedStatus {
+            status: status_byte,
+        }),
+        _ if !matches => Err(ParseError::UnexpectedStatus {
+            status: status_byte,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the status byte, the data bytes and the length of a fixed length message.
+pub(crate) fn check_message(raw_bytes: &[u8], status: u8, length: usize) -> Result<(), ParseError> {
+    check_status(raw_bytes, status)?;
+    if let Some(offset) = raw_bytes.iter().skip(1).position(|&byte| byte > 127) {
+        return Err(ParseError::DataByteOutOfRange {
+            offset: offset + 1,
+            byte: raw_bytes[offset + 1],
+        });
+    }
+    if raw_bytes.len() != length {
+        return Err(ParseError::InvalidLength {
+            status: raw_bytes[0],
+            expected: length - 1,
+            found: raw_bytes.len() - 1,
+        });
+    }
+    Ok(())
+}
+
+/// Checks a channel mode message which is identified by its controller number.
+pub(crate) fn check_channel_mode_message(
+    raw_bytes: &[u8],
+    controller: u8,
+) -> Result<(), ParseError> {
+    check_message(raw_bytes, 0xB0, 3)?;
+    if raw_bytes[1] != controller {
+        return Err(ParseError::UnexpectedController {
+            controller: raw_bytes[1],
+        });
+    }
+    Ok(())
+}
+
+/// A single message which was parsed from raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParsedMessage {
+    pub message: Message,
+    /// System realtime messages which were interleaved with the bytes of the message.
+    pub realtime: Vec<Message>,
+    /// The number of realtime messages which arrived before the message was complete.
+    realtime_before_message: usize,
+}
+
+impl ParsedMessage {
+    /// The number of realtime messages which arrived before the message was complete.
+    pub fn realtime_before_message(&self) -> usize {
+        self.realtime_before_message
+    }
+
+    /// Returns the message and the interleaved realtime messages in the order they were completed.
+    pub fn into_messages(self) -> Vec<Message> {
+        let mut messages = self.realtime;
+        messages.insert(self.realtime_before_message, self.message);
+        messages
+    }
 
-            0xC0 => match data_bytes_length {
-                1 if data_bytes[0] <= 127 => Message::ProgramChange(raw_message.into()),
-                _ => Message::Malformed,
+    fn classify(raw_message: &[u8]) -> Result<Message, ParseError> {
+        let status_byte = *raw_message.first().ok_or(ParseError::Empty)?;
+        let message = match status_byte & 0xF0 {
+            0x80 => Message::NoteOff(raw_message.try_into()?),
+            0x90 => Message::NoteOn(raw_message.try_into()?),
+            0xA0 => Message::PolyAfterTouch(raw_message.try_into()?),
+            0xB0 => match raw_message.get(1) {
+                Some(120) => Message::AllSoundOff(raw_message.try_into()?),
+                Some(121) => Message::ResetAllControllers(raw_message.try_into()?),
+                Some(122) => Message::LocalControl(raw_message.try_into()?),
+                Some(123) => Message::AllNotesOff(raw_message.try_into()?),
+                Some(124) => Message::OmniModeOff(raw_message.try_into()?),
+                Some(125) => Message::OmniModeOn(raw_message.try_into()?),
+                Some(126) => Message::MonoModeOn(raw_message.try_into()?),
+                Some(127) => Message::PolyModeOn(raw_message.try_into()?),
+                _ => Message::ControlChange(raw_message.try_into()?),
             },
-            0xD0 => match data_bytes_length {
-                1 if data_bytes[0] <= 127 => Message::AfterTouch(raw_message.into()),
-                _ => Message::Malformed,
+            0xC0 => Message::ProgramChange(raw_message.try_into()?),
+            0xD0 => Message::AfterTouch(raw_message.try_into()?),
+            0xE0 => Message::PitchBend(raw_message.try_into()?),
+            0xF0 => match status_byte {
+                0xF0 => Message::SystemExclusive(raw_message.try_into()?),
+                0xF1 => Message::TimeCodeQuarterFrame(raw_message.try_into()?),
+                0xF2 => Message::SongPosition(raw_message.try_into()?),
+                0xF3 => Message::SongSelect(raw_message.try_into()?),
+                0xF4 | 0xF5 | 0xF9 | 0xFD => Message::Undefined,
+                0xF6 => Message::TuneRequest(raw_message.try_into()?),
+                0xF7 => Message::EndOfExclusive(raw_message.try_into()?),
+                0xF8 => Message::TimingClock(raw_message.try_into()?),
+                0xFA => Message::Start(raw_message.try_into()?),
+                0xFB => Message::Continue(raw_message.try_into()?),
+                0xFC => Message::Stop(raw_message.try_into()?),
+                0xFE => Message::ActiveSensing(raw_message.try_into()?),
+                _ => Message::Reset(raw_message.try_into()?),
             },
-            0xE0 => match data_bytes_length {
-                2 if data_bytes[0] <= 127 && data_bytes[1] <= 127 => {
-                    Message::PitchBend(raw_message.into())
-                }
-                _ => Message::Malformed,
+            _ => return Err(ParseError::UnexpectedDataByte { offset: 0 }),
+        };
+        Ok(message)
+    }
+}
+
+impl From<&[u8]> for ParsedMessage {
+    fn from(raw_message: &[u8]) -> Self {
+        // Meta events only exist in files, their data may contain any byte.
+        if let [0xFF, 0x00..=0x7F, ..] = raw_message {
+            return ParsedMessage {
+                message: classify_meta(raw_message).unwrap_or_else(Message::Malformed),
+                realtime: vec![],
+                realtime_before_message: 0,
+            };
+        }
+        let mut message_bytes = vec![];
+        // The offsets of the message bytes in the raw message.
+        let mut offsets = vec![];
+        let mut realtime = vec![];
+        let mut realtime_before_message = 0;
+        for (offset, &byte) in raw_message.iter().enumerate() {
+            // System realtime bytes may appear anywhere in a message, they are separate messages.
+            if offset > 0 && byte >= 0xF8 {
+                realtime.push(ParsedMessage::from(&[byte][..]).message);
+            } else {
+                message_bytes.push(byte);
+                offsets.push(offset);
+                realtime_before_message = realtime.len();
+            }
+        }
+
+        let message = Self::classify(&message_bytes[..]).unwrap_or_else(|error| {
+            Message::Malformed(error.map_offset(|offset| {
+                offsets.get(offset).copied().unwrap_or(raw_message.len())
+            }))
+        });
+
+        ParsedMessage {
+            message,
+            realtime,
+            realtime_before_message,
+        }
+    }
+}
+
+/// Returns the full length of a message which starts with the given status byte.
+///
+/// `None` is returned for system exclusive messages which don't have a fixed length.
+pub(crate) fn message_length(status_byte: u8) -> Option<usize> {
+    match status_byte & 0xF0 {
+        0x80 | 0x90 | 0xA0 | 0xB0 | 0xE0 => Some(3),
+        0xC0 | 0xD0 => Some(2),
+        _ => match status_byte {
+            0xF0 => None,
+            0xF1 | 0xF3 => Some(2),
+            0xF2 => Some(3),
+            _ => Some(1),
+        },
+    }
+}
+
+/// Parses a buffer which contains any number of messages.
+///
+/// Running status and system exclusive boundaries are handled the same way as in [StreamParser].
+/// Each message is returned together with the offset of its first byte in the buffer,
+/// a message which is not complete at the end of the buffer is returned as malformed.
+pub fn parse_all(bytes: &[u8]) -> Vec<(usize, Message)> {
+    let mut parser = StreamParser::new();
+    let mut messages = parser.feed_with_offsets(bytes);
+    messages.extend(parser.flush());
+    messages
+}
+
+/// A stateful parser for raw midi byte streams.
+///
+/// Bytes can be fed in chunks of any size, messages which span multiple chunks are
+/// buffered until they are complete. Running status is tracked for channel messages.
+#[derive(Debug, Default, Clone)]
+pub struct StreamParser {
+    buffer: Vec<u8>,
+    running_status: Option<u8>,
+    /// The number of bytes fed since the parser was created or reset.
+    position: usize,
+    /// The position of the first byte of the buffered message.
+    message_offset: usize,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk of bytes to the parser and returns the messages which are completed by it.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Message> {
+        self.feed_with_offsets(bytes)
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    /// Like [StreamParser::feed], but each message is paired with the position of its first byte
+    /// in the stream.
+    pub fn feed_with_offsets(&mut self, bytes: &[u8]) -> Vec<(usize, Message)> {
+        let mut messages = vec![];
+        for &byte in bytes {
+            self.push(byte, &mut messages);
+            self.position += 1;
+        }
+        messages
+    }
+
+    /// Discards the buffered bytes of an incomplete message, returning it as malformed.
+    pub fn flush(&mut self) -> Option<(usize, Message)> {
+        let status_byte = *self.buffer.first()?;
+        let error = match message_length(status_byte) {
+            Some(length) => ParseError::InvalidLength {
+                status: status_byte,
+                expected: length - 1,
+                found: self.buffer.len() - 1,
             },
-            _ => match status_byte {
-                0xF0 => match data_bytes.last() {
-                    Some(0xF7) => Message::SystemExclusive(raw_message.into()),
-                    _ => Message::Malformed,
-                },
-                0xF1 => match data_bytes_length {
-                    1 if data_bytes[0] <= 127 => Message::TimeCodeQuarterFrame(raw_message.into()),
-                    _ => Message::Malformed,
-                },
-                0xF2 => match data_bytes_length {
-                    2 if data_bytes[0] <= 127 && data_bytes[1] <= 127 => {
-                        Message::SongPosition(raw_message.into())
-                    }
-                    _ => Message::Malformed,
-                },
-                0xF3 => match data_bytes_length {
-                    1 if data_bytes[0] <= 127 => Message::SongSelect(raw_message.into()),
-                    _ => Message::Malformed,
-                },
-                0xF4 | 0xF5 => Message::Undefined,
-                0xF6 => match data_bytes_length {
-                    0 => Message::TuneRequest(TuneRequest::default()),
-                    _ => Message::Malformed,
-                },
-                0xF7 => match data_bytes_length {
-                    0 => Message::EndOfExclusive(EndOfExclusive::default()),
-                    _ => Message::Malformed,
-                },
-                0xF8 => match data_bytes_length {
-                    0 => Message::TimingClock(TimingClock::default()),
-                    _ => Message::Malformed,
-                },
-                0xF9 => Message::Undefined,
-                0xFA => match data_bytes_length {
-                    0 => Message::Start(Start::default()),
-                    _ => Message::Malformed,
-                },
-                0xFB => match data_bytes_length {
-                    0 => Message::Continue(Continue::default()),
-                    _ => Message::Malformed,
-                },
-                0xFC => match data_bytes_length {
-                    0 => Message::Stop(Stop::default()),
-                    _ => Message::Malformed,
-                },
-                0xFD => Message::Undefined,
-                0xFE => match data_bytes_length {
-                    0 => Message::ActiveSensing(ActiveSensing::default()),
-                    _ => Message::Malformed,
-                },
-                0xFF => match data_bytes_length {
-                    0 => Message::Reset(Reset::default()),
-                    _ => Message::Malformed,
-                },
-                _ => Message::Malformed,
+            None => ParseError::MissingEndOfExclusive {
+                offset: self.buffer.len(),
             },
         };
+        self.buffer.clear();
+        Some((self.message_offset, Message::Malformed(error)))
+    }
+
+    /// Clears the buffered bytes, the running status and the stream position.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn in_system_exclusive(&self) -> bool {
+        self.buffer.first() == Some(&0xF0)
+    }
+
+    fn push(&mut self, byte: u8, messages: &mut Vec<(usize, Message)>) {
+        // System exclusive messages are collected until end of exclusive,
+        // a status byte other than a system realtime one aborts them.
+        if self.in_system_exclusive() && (byte < 0x80 || byte == 0xF7) {
+            self.buffer.push(byte);
+            if byte == 0xF7 {
+                self.complete(messages);
+            }
+            return;
+        }
+
+        match byte {
+            0x00..=0x7F => {
+                if self.buffer.is_empty() {
+                    match self.running_status {
+                        Some(status_byte) => {
+                            self.buffer.push(status_byte);
+                            self.message_offset = self.position;
+                        }
+                        None => {
+                            // A data byte without a status byte to belong to.
+                            messages.push((
+                                self.position,
+                                Message::Malformed(ParseError::UnexpectedDataByte { offset: 0 }),
+                            ));
+                            return;
+                        }
+                    }
+                }
+                self.buffer.push(byte);
+                if message_length(self.buffer[0]) == Some(self.buffer.len()) {
+                    self.complete(messages);
+                }
+            }
+            // System realtime messages don't interrupt the message being received.
+            0xF8..=0xFF => messages.push((self.position, ParsedMessage::from(&[byte][..]).message)),
+            _ => {
+                // A new status byte arrived before the previous message was complete.
+                messages.extend(self.flush());
+                self.running_status = match byte {
+                    0x80..=0xEF => Some(byte),
+                    _ => None,
+                };
+                self.buffer.push(byte);
+                self.message_offset = self.position;
+                if message_length(byte) == Some(1) {
+                    self.complete(messages);
+                }
+            }
+        }
+    }
 
-        ParsedMessage { message }
+    fn complete(&mut self, messages: &mut Vec<(usize, Message)>) {
+        messages.push((self.message_offset, ParsedMessage::from(&self.buffer[..]).message));
+        self.buffer.clear();
     }
 }
@@ -1,14 +1,22 @@
 use crate::impl_midi_message;
+use crate::check_channel_mode_message;
+use crate::check_range;
 use crate::Category;
 use crate::MidiMessage;
+use crate::ParseError;
+use crate::RangeError;
 
-#[derive(Debug)]
+/// A channel mode message which mutes all sounding notes (controller 120).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AllSoundOff {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl AllSoundOff {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 120, value.min(127) as u8],
@@ -21,14 +29,23 @@ impl AllSoundOff {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for AllSoundOff {
-    fn from(raw_bytes: &[u8]) -> Self {
-        AllSoundOff {
+impl TryFrom<&[u8]> for AllSoundOff {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 120)?;
+        Ok(AllSoundOff {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -41,13 +58,17 @@ impl Default for AllSoundOff {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which resets all controllers to their defaults (controller 121).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ResetAllControllers {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl ResetAllControllers {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 121, value.min(127) as u8],
@@ -60,14 +81,23 @@ impl ResetAllControllers {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for ResetAllControllers {
-    fn from(raw_bytes: &[u8]) -> Self {
-        ResetAllControllers {
+impl TryFrom<&[u8]> for ResetAllControllers {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 121)?;
+        Ok(ResetAllControllers {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -80,13 +110,17 @@ impl Default for ResetAllControllers {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which turns local control on or off (controller 122).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct LocalControl {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl LocalControl {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 122, value.min(127) as u8],
@@ -99,14 +133,23 @@ impl LocalControl {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for LocalControl {
-    fn from(raw_bytes: &[u8]) -> Self {
-        LocalControl {
+impl TryFrom<&[u8]> for LocalControl {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 122)?;
+        Ok(LocalControl {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -119,13 +162,17 @@ impl Default for LocalControl {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which turns off all notes (controller 123).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AllNotesOff {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl AllNotesOff {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 123, value.min(127) as u8],
@@ -138,14 +185,23 @@ impl AllNotesOff {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for AllNotesOff {
-    fn from(raw_bytes: &[u8]) -> Self {
-        AllNotesOff {
+impl TryFrom<&[u8]> for AllNotesOff {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 123)?;
+        Ok(AllNotesOff {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -158,13 +214,17 @@ impl Default for AllNotesOff {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which turns omni mode off (controller 124).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct OmniModeOff {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl OmniModeOff {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 124, value.min(127) as u8],
@@ -177,14 +237,23 @@ impl OmniModeOff {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for OmniModeOff {
-    fn from(raw_bytes: &[u8]) -> Self {
-        OmniModeOff {
+impl TryFrom<&[u8]> for OmniModeOff {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 124)?;
+        Ok(OmniModeOff {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -197,13 +266,17 @@ impl Default for OmniModeOff {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which turns omni mode on (controller 125).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct OmniModeOn {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl OmniModeOn {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 125, value.min(127) as u8],
@@ -216,14 +289,23 @@ impl OmniModeOn {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for OmniModeOn {
-    fn from(raw_bytes: &[u8]) -> Self {
-        OmniModeOn {
+impl TryFrom<&[u8]> for OmniModeOn {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 125)?;
+        Ok(OmniModeOn {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -236,13 +318,17 @@ impl Default for OmniModeOn {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which turns mono mode on (controller 126).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MonoModeOn {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl MonoModeOn {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 126, value.min(127) as u8],
@@ -255,14 +341,23 @@ impl MonoModeOn {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for MonoModeOn {
-    fn from(raw_bytes: &[u8]) -> Self {
-        MonoModeOn {
+impl TryFrom<&[u8]> for MonoModeOn {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 126)?;
+        Ok(MonoModeOn {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
@@ -275,13 +370,17 @@ impl Default for MonoModeOn {
     }
 }
 
-#[derive(Debug)]
+/// A channel mode message which turns poly mode on (controller 127).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PolyModeOn {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl PolyModeOn {
+    pub fn try_new(value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("value", value, 127)?, check_range("channel", channel, 15)?))
+    }
     pub fn new(value: u64, channel: u64) -> Self {
         Self {
             bytes: [0xB0 | channel.min(15) as u8, 127, value.min(127) as u8],
@@ -294,14 +393,23 @@ impl PolyModeOn {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for PolyModeOn {
-    fn from(raw_bytes: &[u8]) -> Self {
-        PolyModeOn {
+impl TryFrom<&[u8]> for PolyModeOn {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_channel_mode_message(raw_bytes, 127)?;
+        Ok(PolyModeOn {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelMode,
-        }
+        })
     }
 }
 
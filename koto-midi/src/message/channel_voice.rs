@@ -1,13 +1,25 @@
 use crate::impl_midi_message;
+use crate::check_message;
+use crate::check_range;
 use crate::Category;
 use crate::MidiMessage;
+use crate::ParseError;
+use crate::RangeError;
 
-#[derive(Debug)]
+/// A note off message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NoteOff {
     bytes: [u8; 3],
     pub category: Category,
 }
 impl NoteOff {
+    pub fn try_new(note: u64, velocity: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("note", note, 127)?,
+            check_range("velocity", velocity, 127)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
     pub fn new(note: u64, velocity: u64, channel: u64) -> Self {
         Self {
             bytes: [
@@ -27,14 +39,26 @@ impl NoteOff {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_note(&mut self, note: u8) {
+        self.bytes[1] = note.min(127);
+    }
+    pub fn change_velocity(&mut self, velocity: u8) {
+        self.bytes[2] = velocity.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for NoteOff {
-    fn from(raw_bytes: &[u8]) -> Self {
-        NoteOff {
+impl TryFrom<&[u8]> for NoteOff {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0x80, 3)?;
+        Ok(NoteOff {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
@@ -47,12 +71,20 @@ impl Default for NoteOff {
     }
 }
 
-#[derive(Debug)]
+/// A note on message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NoteOn {
     bytes: [u8; 3],
     pub category: Category,
 }
 impl NoteOn {
+    pub fn try_new(note: u64, velocity: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("note", note, 127)?,
+            check_range("velocity", velocity, 127)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
     pub fn new(note: u64, velocity: u64, channel: u64) -> Self {
         Self {
             bytes: [
@@ -72,14 +104,26 @@ impl NoteOn {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_note(&mut self, note: u8) {
+        self.bytes[1] = note.min(127);
+    }
+    pub fn change_velocity(&mut self, velocity: u8) {
+        self.bytes[2] = velocity.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for NoteOn {
-    fn from(raw_bytes: &[u8]) -> Self {
-        NoteOn {
+impl TryFrom<&[u8]> for NoteOn {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0x90, 3)?;
+        Ok(NoteOn {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
@@ -92,13 +136,21 @@ impl Default for NoteOn {
     }
 }
 
-#[derive(Debug)]
+/// A polyphonic key pressure message for a single note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PolyAfterTouch {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl PolyAfterTouch {
+    pub fn try_new(note: u64, pressure: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("note", note, 127)?,
+            check_range("pressure", pressure, 127)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
     pub fn new(note: u64, pressure: u64, channel: u64) -> Self {
         Self {
             bytes: [
@@ -118,14 +170,26 @@ impl PolyAfterTouch {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_note(&mut self, note: u8) {
+        self.bytes[1] = note.min(127);
+    }
+    pub fn change_pressure(&mut self, pressure: u8) {
+        self.bytes[2] = pressure.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for PolyAfterTouch {
-    fn from(raw_bytes: &[u8]) -> Self {
-        PolyAfterTouch {
+impl TryFrom<&[u8]> for PolyAfterTouch {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xA0, 3)?;
+        Ok(PolyAfterTouch {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
@@ -138,40 +202,68 @@ impl Default for PolyAfterTouch {
     }
 }
 
-#[derive(Debug)]
+/// A control change message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ControlChange {
     bytes: [u8; 3],
     pub category: Category,
 }
 
 impl ControlChange {
-    pub fn new(note: u64, value: u64, channel: u64) -> Self {
+    pub fn try_new(controller: u64, value: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("controller", controller, 127)?,
+            check_range("value", value, 127)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
+    pub fn new(controller: u64, value: u64, channel: u64) -> Self {
         Self {
             bytes: [
                 0xB0 | channel.min(15) as u8,
-                note.min(127) as u8,
+                controller.min(127) as u8,
                 value.min(127) as u8,
             ],
             category: Category::ChannelVoice,
         }
     }
-    pub fn note(&self) -> u8 {
+    pub fn controller(&self) -> u8 {
         self.bytes[1]
     }
+    #[deprecated(note = "use controller instead")]
+    pub fn note(&self) -> u8 {
+        self.controller()
+    }
     pub fn value(&self) -> u8 {
         self.bytes[2]
     }
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_controller(&mut self, controller: u8) {
+        self.bytes[1] = controller.min(127);
+    }
+    #[deprecated(note = "use change_controller instead")]
+    pub fn change_note(&mut self, note: u8) {
+        self.change_controller(note);
+    }
+    pub fn change_value(&mut self, value: u8) {
+        self.bytes[2] = value.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for ControlChange {
-    fn from(raw_bytes: &[u8]) -> Self {
-        ControlChange {
+impl TryFrom<&[u8]> for ControlChange {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xB0, 3)?;
+        Ok(ControlChange {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
@@ -184,13 +276,20 @@ impl Default for ControlChange {
     }
 }
 
-#[derive(Debug)]
+/// A program change message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ProgramChange {
     bytes: [u8; 2],
     pub category: Category,
 }
 
 impl ProgramChange {
+    pub fn try_new(program: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("program", program, 127)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
     pub fn new(program: u64, channel: u64) -> Self {
         Self {
             bytes: [0xC0 | channel.min(15) as u8, program.min(127) as u8],
@@ -203,14 +302,23 @@ impl ProgramChange {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_program(&mut self, program: u8) {
+        self.bytes[1] = program.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for ProgramChange {
-    fn from(raw_bytes: &[u8]) -> Self {
-        ProgramChange {
+impl TryFrom<&[u8]> for ProgramChange {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xC0, 2)?;
+        Ok(ProgramChange {
             bytes: [raw_bytes[0], raw_bytes[1]],
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
@@ -223,16 +331,23 @@ impl Default for ProgramChange {
     }
 }
 
-#[derive(Debug)]
+/// A channel pressure message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AfterTouch {
     bytes: [u8; 2],
     pub category: Category,
 }
 
 impl AfterTouch {
+    pub fn try_new(pressure: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("pressure", pressure, 127)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
     pub fn new(pressure: u64, channel: u64) -> Self {
         Self {
-            bytes: [0xC0 | channel.min(15) as u8, pressure.min(127) as u8],
+            bytes: [0xD0 | channel.min(15) as u8, pressure.min(127) as u8],
             category: Category::ChannelVoice,
         }
     }
@@ -242,14 +357,23 @@ impl AfterTouch {
     pub fn channel(&self) -> u8 {
         self.bytes[0] & 0x0F
     }
+    pub fn change_pressure(&mut self, pressure: u8) {
+        self.bytes[1] = pressure.min(127);
+    }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for AfterTouch {
-    fn from(raw_bytes: &[u8]) -> Self {
-        AfterTouch {
+impl TryFrom<&[u8]> for AfterTouch {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xD0, 2)?;
+        Ok(AfterTouch {
             bytes: [raw_bytes[0], raw_bytes[1]],
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
@@ -262,7 +386,8 @@ impl Default for AfterTouch {
     }
 }
 
-#[derive(Debug)]
+/// A pitch bend message with a 14 bit bend amount, 8192 is the center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PitchBend {
     bytes: [u8; 3],
     bend_amount: u16,
@@ -270,6 +395,12 @@ pub struct PitchBend {
 }
 
 impl PitchBend {
+    pub fn try_new(bend_amount: u64, channel: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("bend_amount", bend_amount, 16383)?,
+            check_range("channel", channel, 15)?,
+        ))
+    }
     pub fn new(bend_amount: u64, channel: u64) -> Self {
         let bend_amount = bend_amount.min(16383) as u16;
         let msb = ((bend_amount >> 7) as u8) & 0b0111_1111;
@@ -288,28 +419,34 @@ impl PitchBend {
     }
     pub fn change_bend_amount(&mut self, bend_amount: u16) {
         self.bend_amount = bend_amount.min(16383);
-        let msb = ((bend_amount >> 7) as u8) & 0b0111_1111;
-        let lsb = (bend_amount as u8) & 0b0111_1111;
+        let msb = ((self.bend_amount >> 7) as u8) & 0b0111_1111;
+        let lsb = (self.bend_amount as u8) & 0b0111_1111;
         self.bytes[1] = lsb;
         self.bytes[2] = msb;
     }
+    pub fn change_channel(&mut self, channel: u8) {
+        self.bytes[0] = (self.bytes[0] & 0xF0) | channel.min(15);
+    }
 }
 
-impl From<&[u8]> for PitchBend {
-    fn from(raw_bytes: &[u8]) -> Self {
+impl TryFrom<&[u8]> for PitchBend {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xE0, 3)?;
         let bend_amount = ((raw_bytes[2] as u16) << 7) | raw_bytes[1] as u16;
-        PitchBend {
+        Ok(PitchBend {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             bend_amount,
             category: Category::ChannelVoice,
-        }
+        })
     }
 }
 
 impl Default for PitchBend {
     fn default() -> Self {
         // Middle point
-        let bend_amount = 8821_u16;
+        let bend_amount = 8192_u16;
         let msb = ((bend_amount >> 7) as u8) & 0b0111_1111;
         let lsb = (bend_amount as u8) & 0b0111_1111;
 
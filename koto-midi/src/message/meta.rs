@@ -0,0 +1,662 @@
+use crate::check_power_of_two;
+use crate::check_range;
+use crate::check_status;
+use crate::impl_midi_message;
+use crate::manufacturer;
+use crate::Category;
+use crate::Message;
+use crate::MidiMessage;
+use crate::ParseError;
+use crate::RangeError;
+
+/// The largest value a variable length quantity can hold.
+pub(crate) const MAX_VARIABLE_LENGTH: u32 = 0x0FFFFFFF;
+
+/// Appends a variable length quantity, values above [MAX_VARIABLE_LENGTH] are clamped.
+pub(crate) fn write_variable_length(bytes: &mut Vec<u8>, value: u32) {
+    let value = value.min(MAX_VARIABLE_LENGTH);
+    let mut shift = 21;
+    while shift > 0 && value >> shift == 0 {
+        shift -= 7;
+    }
+    while shift > 0 {
+        bytes.push(((value >> shift) & 0x7F) as u8 | 0x80);
+        shift -= 7;
+    }
+    bytes.push((value & 0x7F) as u8);
+}
+
+/// Reads a variable length quantity and returns it with the number of bytes it takes.
+pub(crate) fn read_variable_length(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (i, &byte) in bytes.iter().take(4).enumerate() {
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn meta_bytes(meta_type: u8, data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xFF, meta_type];
+    write_variable_length(&mut bytes, data.len() as u32);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// The data of a meta event which was checked by [check_meta].
+fn meta_data(bytes: &[u8]) -> &[u8] {
+    match read_variable_length(&bytes[2..]) {
+        Some((_, length_size)) => &bytes[2 + length_size..],
+        None => &[],
+    }
+}
+
+/// Checks the status byte, the type and the length of a meta event and returns its data.
+pub(crate) fn check_meta(raw_bytes: &[u8], meta_type: u8) -> Result<&[u8], ParseError> {
+    check_status(raw_bytes, 0xFF)?;
+    let invalid_length = |expected| ParseError::InvalidLength {
+        status: 0xFF,
+        expected,
+        found: raw_bytes.len() - 1,
+    };
+    match raw_bytes.get(1) {
+        Some(&found) if found == meta_type => {}
+        Some(&found) => return Err(ParseError::UnexpectedMetaType { meta_type: found }),
+        None => return Err(invalid_length(2)),
+    }
+    let (length, length_size) = read_variable_length(&raw_bytes[2..]).ok_or(invalid_length(2))?;
+    let start = 2 + length_size;
+    if raw_bytes.len() != start + length as usize {
+        return Err(invalid_length(start - 1 + length as usize));
+    }
+    Ok(&raw_bytes[start..])
+}
+
+/// Checks the data of a meta event which has a fixed length.
+fn check_meta_data(data: &[u8], meta_type: u8, length: usize) -> Result<(), ParseError> {
+    if data.len() != length {
+        return Err(ParseError::InvalidMetaData { meta_type });
+    }
+    Ok(())
+}
+
+/// Classifies a meta event by its type.
+pub(crate) fn classify_meta(raw_message: &[u8]) -> Result<Message, ParseError> {
+    let message = match raw_message.get(1).copied().unwrap_or_default() {
+        Text::META_TYPE => Message::Text(raw_message.try_into()?),
+        Copyright::META_TYPE => Message::Copyright(raw_message.try_into()?),
+        TrackName::META_TYPE => Message::TrackName(raw_message.try_into()?),
+        InstrumentName::META_TYPE => Message::InstrumentName(raw_message.try_into()?),
+        Lyric::META_TYPE => Message::Lyric(raw_message.try_into()?),
+        Marker::META_TYPE => Message::Marker(raw_message.try_into()?),
+        CuePoint::META_TYPE => Message::CuePoint(raw_message.try_into()?),
+        EndOfTrack::META_TYPE => Message::EndOfTrack(raw_message.try_into()?),
+        Tempo::META_TYPE => Message::Tempo(raw_message.try_into()?),
+        SmpteOffset::META_TYPE => Message::SmpteOffset(raw_message.try_into()?),
+        TimeSignature::META_TYPE => Message::TimeSignature(raw_message.try_into()?),
+        KeySignature::META_TYPE => Message::KeySignature(raw_message.try_into()?),
+        SequencerSpecific::META_TYPE => Message::SequencerSpecific(raw_message.try_into()?),
+        meta_type => return Err(ParseError::UnexpectedMetaType { meta_type }),
+    };
+    Ok(message)
+}
+
+macro_rules! text_meta_event {
+    ($(#[$doc:meta])* $type:ident, $meta_type:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $type {
+            bytes: Vec<u8>,
+            pub category: Category,
+        }
+
+        impl $type {
+            pub const META_TYPE: u8 = $meta_type;
+
+            pub fn new(text: &str) -> Self {
+                Self::from_data(text.as_bytes())
+            }
+            /// Files don't declare the encoding of their text, so the bytes are kept as they are.
+            pub fn from_data(data: &[u8]) -> Self {
+                Self {
+                    bytes: meta_bytes($meta_type, data),
+                    category: Category::Meta,
+                }
+            }
+            /// The text, bytes which aren't valid utf-8 are replaced.
+            pub fn text(&self) -> String {
+                String::from_utf8_lossy(self.data()).into_owned()
+            }
+            pub fn data(&self) -> &[u8] {
+                meta_data(&self.bytes)
+            }
+            pub fn change_text(&mut self, text: &str) {
+                *self = Self::new(text);
+            }
+        }
+
+        impl TryFrom<&[u8]> for $type {
+            type Error = ParseError;
+
+            fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+                let data = check_meta(raw_bytes, $meta_type)?;
+                Ok(Self::from_data(data))
+            }
+        }
+
+        impl Default for $type {
+            fn default() -> Self {
+                Self::new("")
+            }
+        }
+    };
+}
+
+text_meta_event!(
+    /// Any text, like a description of the track.
+    Text,
+    0x01
+);
+text_meta_event!(
+    /// A copyright notice.
+    Copyright,
+    0x02
+);
+text_meta_event!(
+    /// The name of a sequence in the first track of a file, the name of a track in the others.
+    TrackName,
+    0x03
+);
+text_meta_event!(
+    /// The name of the instrument a track is meant for.
+    InstrumentName,
+    0x04
+);
+text_meta_event!(
+    /// A syllable of the lyrics which is sung at the time of the event.
+    Lyric,
+    0x05
+);
+text_meta_event!(
+    /// The name of a point in a sequence, like a rehearsal letter or the start of a verse.
+    Marker,
+    0x06
+);
+text_meta_event!(
+    /// A description of something which happens on stage or in the film at the time of the event.
+    CuePoint,
+    0x07
+);
+
+/// The end of a track, every track of a file ends with one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EndOfTrack {
+    bytes: [u8; 3],
+    pub category: Category,
+}
+
+impl Default for EndOfTrack {
+    fn default() -> Self {
+        EndOfTrack {
+            bytes: [0xFF, 0x2F, 0x00],
+            category: Category::Meta,
+        }
+    }
+}
+
+impl EndOfTrack {
+    pub const META_TYPE: u8 = 0x2F;
+
+    pub fn new() -> Self {
+        EndOfTrack::default()
+    }
+}
+
+impl TryFrom<&[u8]> for EndOfTrack {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_meta_data(check_meta(raw_bytes, 0x2F)?, 0x2F, 0)?;
+        Ok(EndOfTrack::default())
+    }
+}
+
+/// A tempo change in microseconds per quarter note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tempo {
+    bytes: [u8; 6],
+    pub category: Category,
+}
+
+impl Tempo {
+    pub const META_TYPE: u8 = 0x51;
+
+    pub fn try_new(microseconds_per_quarter_note: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range(
+            "microseconds_per_quarter_note",
+            microseconds_per_quarter_note,
+            0xFFFFFF,
+        )?))
+    }
+    pub fn new(microseconds_per_quarter_note: u64) -> Self {
+        let [_, high, middle, low] =
+            (microseconds_per_quarter_note.min(0xFFFFFF) as u32).to_be_bytes();
+        Self {
+            bytes: [0xFF, 0x51, 0x03, high, middle, low],
+            category: Category::Meta,
+        }
+    }
+    /// The tempo is rounded to the nearest microsecond per quarter note.
+    pub fn from_beats_per_minute(beats_per_minute: f64) -> Self {
+        Self::new((60_000_000.0 / beats_per_minute).round() as u64)
+    }
+    pub fn microseconds_per_quarter_note(&self) -> u32 {
+        u32::from_be_bytes([0, self.bytes[3], self.bytes[4], self.bytes[5]])
+    }
+    pub fn beats_per_minute(&self) -> f64 {
+        60_000_000.0 / self.microseconds_per_quarter_note().max(1) as f64
+    }
+    pub fn change_microseconds_per_quarter_note(&mut self, microseconds_per_quarter_note: u32) {
+        *self = Self::new(microseconds_per_quarter_note as u64);
+    }
+}
+
+impl TryFrom<&[u8]> for Tempo {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let data = check_meta(raw_bytes, 0x51)?;
+        check_meta_data(data, 0x51, 3)?;
+        Ok(Self::new(
+            u32::from_be_bytes([0, data[0], data[1], data[2]]) as u64,
+        ))
+    }
+}
+
+impl Default for Tempo {
+    /// 120 beats per minute.
+    fn default() -> Self {
+        Self::new(500_000)
+    }
+}
+
+/// The offset of the start of a track from the start of a timecode.
+///
+/// The rate is 0 for 24, 1 for 25, 2 for 30 drop frame (29.97) and 3 for 30 frames per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SmpteOffset {
+    bytes: [u8; 8],
+    pub category: Category,
+}
+
+impl SmpteOffset {
+    pub const META_TYPE: u8 = 0x54;
+
+    pub fn try_new(
+        hours: u64,
+        minutes: u64,
+        seconds: u64,
+        frames: u64,
+        fractional_frames: u64,
+        rate: u64,
+    ) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("hours", hours, 23)?,
+            check_range("minutes", minutes, 59)?,
+            check_range("seconds", seconds, 59)?,
+            check_range("frames", frames, 29)?,
+            check_range("fractional_frames", fractional_frames, 99)?,
+            check_range("rate", rate, 3)?,
+        ))
+    }
+    pub fn new(
+        hours: u64,
+        minutes: u64,
+        seconds: u64,
+        frames: u64,
+        fractional_frames: u64,
+        rate: u64,
+    ) -> Self {
+        Self {
+            bytes: [
+                0xFF,
+                0x54,
+                0x05,
+                (rate.min(3) << 5) as u8 | hours.min(23) as u8,
+                minutes.min(59) as u8,
+                seconds.min(59) as u8,
+                frames.min(29) as u8,
+                fractional_frames.min(99) as u8,
+            ],
+            category: Category::Meta,
+        }
+    }
+    pub fn hours(&self) -> u8 {
+        self.bytes[3] & 0x1F
+    }
+    pub fn minutes(&self) -> u8 {
+        self.bytes[4]
+    }
+    pub fn seconds(&self) -> u8 {
+        self.bytes[5]
+    }
+    pub fn frames(&self) -> u8 {
+        self.bytes[6]
+    }
+    /// Hundredths of a frame.
+    pub fn fractional_frames(&self) -> u8 {
+        self.bytes[7]
+    }
+    pub fn rate(&self) -> u8 {
+        self.bytes[3] >> 5
+    }
+    pub fn change_hours(&mut self, hours: u8) {
+        self.bytes[3] = (self.bytes[3] & 0x60) | hours.min(23);
+    }
+    pub fn change_minutes(&mut self, minutes: u8) {
+        self.bytes[4] = minutes.min(59);
+    }
+    pub fn change_seconds(&mut self, seconds: u8) {
+        self.bytes[5] = seconds.min(59);
+    }
+    pub fn change_frames(&mut self, frames: u8) {
+        self.bytes[6] = frames.min(29);
+    }
+    pub fn change_fractional_frames(&mut self, fractional_frames: u8) {
+        self.bytes[7] = fractional_frames.min(99);
+    }
+    pub fn change_rate(&mut self, rate: u8) {
+        self.bytes[3] = (rate.min(3) << 5) | (self.bytes[3] & 0x1F);
+    }
+}
+
+impl TryFrom<&[u8]> for SmpteOffset {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let data = check_meta(raw_bytes, 0x54)?;
+        check_meta_data(data, 0x54, 5)?;
+        let [hours_and_rate, minutes, seconds, frames, fractional_frames] = data[..] else {
+            unreachable!()
+        };
+        let hours = hours_and_rate & 0x1F;
+        if hours_and_rate > 0x7F
+            || hours > 23
+            || minutes > 59
+            || seconds > 59
+            || frames > 29
+            || fractional_frames > 99
+        {
+            return Err(ParseError::InvalidMetaData { meta_type: 0x54 });
+        }
+        Ok(Self::new(
+            hours as u64,
+            minutes as u64,
+            seconds as u64,
+            frames as u64,
+            fractional_frames as u64,
+            (hours_and_rate >> 5) as u64,
+        ))
+    }
+}
+
+impl Default for SmpteOffset {
+    fn default() -> Self {
+        Self::new(0, 0, 0, 0, 0, 0)
+    }
+}
+
+/// A time signature like 6/8.
+///
+/// The clocks per click are the midi clocks between metronome clicks, the 32nd notes per quarter
+/// note are the notated 32nd notes in a quarter note of 24 midi clocks. Most files use 24 and 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeSignature {
+    bytes: [u8; 7],
+    pub category: Category,
+}
+
+impl TimeSignature {
+    pub const META_TYPE: u8 = 0x58;
+
+    pub fn try_new(
+        numerator: u64,
+        denominator: u64,
+        clocks_per_click: u64,
+        thirty_seconds_per_quarter_note: u64,
+    ) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("numerator", numerator, 255)?,
+            check_power_of_two("denominator", denominator, 128)?,
+            check_range("clocks_per_click", clocks_per_click, 255)?,
+            check_range(
+                "thirty_seconds_per_quarter_note",
+                thirty_seconds_per_quarter_note,
+                255,
+            )?,
+        ))
+    }
+    /// The denominator is rounded down to a power of 2.
+    pub fn new(
+        numerator: u64,
+        denominator: u64,
+        clocks_per_click: u64,
+        thirty_seconds_per_quarter_note: u64,
+    ) -> Self {
+        Self {
+            bytes: [
+                0xFF,
+                0x58,
+                0x04,
+                numerator.min(255) as u8,
+                denominator.clamp(1, 128).ilog2() as u8,
+                clocks_per_click.min(255) as u8,
+                thirty_seconds_per_quarter_note.min(255) as u8,
+            ],
+            category: Category::Meta,
+        }
+    }
+    pub fn numerator(&self) -> u8 {
+        self.bytes[3]
+    }
+    pub fn denominator(&self) -> u8 {
+        1 << self.bytes[4]
+    }
+    pub fn clocks_per_click(&self) -> u8 {
+        self.bytes[5]
+    }
+    pub fn thirty_seconds_per_quarter_note(&self) -> u8 {
+        self.bytes[6]
+    }
+    pub fn change_numerator(&mut self, numerator: u8) {
+        self.bytes[3] = numerator;
+    }
+    pub fn change_denominator(&mut self, denominator: u8) {
+        self.bytes[4] = denominator.clamp(1, 128).ilog2() as u8;
+    }
+    pub fn change_clocks_per_click(&mut self, clocks_per_click: u8) {
+        self.bytes[5] = clocks_per_click;
+    }
+    pub fn change_thirty_seconds_per_quarter_note(&mut self, thirty_seconds_per_quarter_note: u8) {
+        self.bytes[6] = thirty_seconds_per_quarter_note;
+    }
+}
+
+impl TryFrom<&[u8]> for TimeSignature {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let data = check_meta(raw_bytes, 0x58)?;
+        check_meta_data(data, 0x58, 4)?;
+        if data[1] > 7 {
+            return Err(ParseError::InvalidMetaData { meta_type: 0x58 });
+        }
+        Ok(TimeSignature {
+            bytes: [0xFF, 0x58, 0x04, data[0], data[1], data[2], data[3]],
+            category: Category::Meta,
+        })
+    }
+}
+
+impl Default for TimeSignature {
+    /// 4/4 with a click every quarter note.
+    fn default() -> Self {
+        Self::new(4, 4, 24, 8)
+    }
+}
+
+/// A key signature, which is either a number of sharps or a number of flats in a major or minor key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeySignature {
+    bytes: [u8; 5],
+    pub category: Category,
+}
+
+impl KeySignature {
+    pub const META_TYPE: u8 = 0x59;
+
+    pub fn try_new(sharps: u64, flats: u64, minor: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("sharps", sharps, 7)?,
+            check_range("flats", flats, 7)?,
+            check_range("minor", minor, 1)?,
+        ))
+    }
+    /// When both sharps and flats are given the sharps are kept, `minor` is 1 for minor keys.
+    pub fn new(sharps: u64, flats: u64, minor: u64) -> Self {
+        let key = if sharps > 0 {
+            sharps.min(7) as i8
+        } else {
+            -(flats.min(7) as i8)
+        };
+        Self {
+            bytes: [0xFF, 0x59, 0x02, key as u8, minor.min(1) as u8],
+            category: Category::Meta,
+        }
+    }
+    /// The number of sharps, or the negated number of flats.
+    pub fn key(&self) -> i8 {
+        self.bytes[3] as i8
+    }
+    pub fn sharps(&self) -> u8 {
+        self.key().max(0) as u8
+    }
+    pub fn flats(&self) -> u8 {
+        self.key().min(0).unsigned_abs()
+    }
+    pub fn is_minor(&self) -> bool {
+        self.bytes[4] == 1
+    }
+    /// Setting sharps removes the flats, setting 0 sharps leaves the flats as they are.
+    pub fn change_sharps(&mut self, sharps: u8) {
+        if sharps > 0 || self.key() > 0 {
+            self.bytes[3] = sharps.min(7);
+        }
+    }
+    /// Setting flats removes the sharps, setting 0 flats leaves the sharps as they are.
+    pub fn change_flats(&mut self, flats: u8) {
+        if flats > 0 || self.key() < 0 {
+            self.bytes[3] = (-(flats.min(7) as i8)) as u8;
+        }
+    }
+    pub fn change_minor(&mut self, minor: bool) {
+        self.bytes[4] = minor as u8;
+    }
+}
+
+impl TryFrom<&[u8]> for KeySignature {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let data = check_meta(raw_bytes, 0x59)?;
+        check_meta_data(data, 0x59, 2)?;
+        if !(-7..=7).contains(&(data[0] as i8)) || data[1] > 1 {
+            return Err(ParseError::InvalidMetaData { meta_type: 0x59 });
+        }
+        Ok(KeySignature {
+            bytes: [0xFF, 0x59, 0x02, data[0], data[1]],
+            category: Category::Meta,
+        })
+    }
+}
+
+impl Default for KeySignature {
+    /// C major.
+    fn default() -> Self {
+        Self::new(0, 0, 0)
+    }
+}
+
+/// Data for a particular sequencer, which starts with the id of its manufacturer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SequencerSpecific {
+    bytes: Vec<u8>,
+    pub category: Category,
+}
+
+impl SequencerSpecific {
+    pub const META_TYPE: u8 = 0x7F;
+
+    pub fn new(manufacturer_id: &[u8], message_content: &[u8]) -> Self {
+        let data = [manufacturer_id, message_content].concat();
+        Self {
+            bytes: meta_bytes(0x7F, &data),
+            category: Category::Meta,
+        }
+    }
+    /// A single byte, or 3 bytes which start with 0.
+    pub fn manufacturer_id(&self) -> &[u8] {
+        let data = self.data();
+        &data[..manufacturer::id_length(data)]
+    }
+    pub fn message_content(&self) -> &[u8] {
+        let data = self.data();
+        &data[manufacturer::id_length(data)..]
+    }
+    pub fn data(&self) -> &[u8] {
+        meta_data(&self.bytes)
+    }
+    pub fn change_manufacturer_id(&mut self, manufacturer_id: &[u8]) {
+        *self = Self::new(manufacturer_id, self.message_content());
+    }
+    pub fn change_message_content(&mut self, message_content: &[u8]) {
+        *self = Self::new(self.manufacturer_id(), message_content);
+    }
+}
+
+impl TryFrom<&[u8]> for SequencerSpecific {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let data = check_meta(raw_bytes, 0x7F)?;
+        if data.is_empty() || (data[0] == 0 && data.len() < 3) {
+            return Err(ParseError::InvalidMetaData { meta_type: 0x7F });
+        }
+        Ok(SequencerSpecific {
+            bytes: meta_bytes(0x7F, data),
+            category: Category::Meta,
+        })
+    }
+}
+
+impl Default for SequencerSpecific {
+    fn default() -> Self {
+        Self::new(&[0x01], &[])
+    }
+}
+
+impl_midi_message!(Text);
+impl_midi_message!(Copyright);
+impl_midi_message!(TrackName);
+impl_midi_message!(InstrumentName);
+impl_midi_message!(Lyric);
+impl_midi_message!(Marker);
+impl_midi_message!(CuePoint);
+impl_midi_message!(EndOfTrack);
+impl_midi_message!(Tempo);
+impl_midi_message!(SmpteOffset);
+impl_midi_message!(TimeSignature);
+impl_midi_message!(KeySignature);
+impl_midi_message!(SequencerSpecific);
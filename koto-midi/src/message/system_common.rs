@@ -1,50 +1,147 @@
 use crate::impl_midi_message;
+use crate::manufacturer;
+use crate::manufacturer::ManufacturerIdError;
+use crate::check_message;
+use crate::check_status;
+use crate::check_range;
 use crate::Category;
 use crate::MidiMessage;
+use crate::ParseError;
+use crate::RangeError;
 
-#[derive(Debug)]
+/// The reason a system exclusive message can't be built from the given bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemExclusiveError {
+    ManufacturerId(ManufacturerIdError),
+    /// A content byte is greater than 127.
+    Content(RangeError),
+}
+
+impl std::fmt::Display for SystemExclusiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SystemExclusiveError::ManufacturerId(error) => write!(f, "{error}"),
+            SystemExclusiveError::Content(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for SystemExclusiveError {}
+
+impl From<ManufacturerIdError> for SystemExclusiveError {
+    fn from(error: ManufacturerIdError) -> Self {
+        SystemExclusiveError::ManufacturerId(error)
+    }
+}
+
+impl From<RangeError> for SystemExclusiveError {
+    fn from(error: RangeError) -> Self {
+        SystemExclusiveError::Content(error)
+    }
+}
+
+/// A system exclusive message, including its start and end of exclusive bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SystemExclusive {
     bytes: Vec<u8>,
-    pub manufacturer_id: Vec<u8>,
+    manufacturer_id: Vec<u8>,
     pub category: Category,
 }
 impl SystemExclusive {
-    pub fn new(manufacturer_id: &[u8], message_content: &[u8]) -> Self {
-        let mut message = message_content.to_vec();
-        message.insert(0, 0xF0);
-        if manufacturer_id.len() == 1 {
-            message.insert(1, manufacturer_id[0]);
-        } else {
-            message.insert(1, manufacturer_id[0]);
-            message.insert(2, manufacturer_id[1]);
-            message.insert(3, manufacturer_id[2]);
+    /// Checks the manufacturer id with [manufacturer::validate] and that the content only has
+    /// data bytes, 8 bit data can be encoded with [crate::packing] first.
+    pub fn try_new(
+        manufacturer_id: &[u8],
+        message_content: &[u8],
+    ) -> Result<Self, SystemExclusiveError> {
+        manufacturer::validate(manufacturer_id)?;
+        for &byte in message_content {
+            check_range("message_content", byte as u64, 127)?;
         }
-
-        message.push(0xF7);
+        Ok(Self::new(manufacturer_id, message_content))
+    }
+    /// Content bytes above 127 are clamped to 127, which loses the data they carried, 8 bit data
+    /// should be encoded with [crate::packing::pack7] or [crate::packing::nibblize] first.
+    ///
+    /// The manufacturer id is used as it is, an id which [manufacturer::validate] rejects makes
+    /// bytes which parse back differently, like `[0x00]` which starts an extended id.
+    pub fn new(manufacturer_id: &[u8], message_content: &[u8]) -> Self {
+        let message_content: Vec<u8> = message_content.iter().map(|&byte| byte.min(127)).collect();
         Self {
-            bytes: message,
+            bytes: [&[0xF0], manufacturer_id, &message_content, &[0xF7]].concat(),
             manufacturer_id: manufacturer_id.to_vec(),
             category: Category::SystemCommon,
         }
     }
+    /// A single byte, or 3 bytes which start with 0.
+    pub fn manufacturer_id(&self) -> &[u8] {
+        &self.manufacturer_id
+    }
+    /// The name of the manufacturer when the registry knows it.
+    pub fn manufacturer_name(&self) -> Option<&'static str> {
+        manufacturer::name(&self.manufacturer_id)
+    }
+    /// The bytes between the manufacturer id and the end of exclusive byte.
+    pub fn message_content(&self) -> &[u8] {
+        &self.bytes[1 + self.manufacturer_id.len()..self.bytes.len() - 1]
+    }
+    pub fn change_manufacturer_id(&mut self, manufacturer_id: &[u8]) {
+        *self = Self::new(manufacturer_id, self.message_content());
+    }
+    /// Checks the manufacturer id with [manufacturer::validate] before changing it.
+    pub fn try_change_manufacturer_id(
+        &mut self,
+        manufacturer_id: &[u8],
+    ) -> Result<(), ManufacturerIdError> {
+        manufacturer::validate(manufacturer_id)?;
+        self.change_manufacturer_id(manufacturer_id);
+        Ok(())
+    }
+    pub fn change_message_content(&mut self, message_content: &[u8]) {
+        *self = Self::new(&self.manufacturer_id.clone(), message_content);
+    }
+    /// Checks that the content only has data bytes before changing it.
+    pub fn try_change_message_content(&mut self, message_content: &[u8]) -> Result<(), RangeError> {
+        for &byte in message_content {
+            check_range("message_content", byte as u64, 127)?;
+        }
+        self.change_message_content(message_content);
+        Ok(())
+    }
 }
 
-impl From<&[u8]> for SystemExclusive {
-    fn from(raw_bytes: &[u8]) -> Self {
-        let mut manufacturer_id: Vec<u8> = vec![];
-        if raw_bytes[1] != 0 {
-            manufacturer_id.push(raw_bytes[1]);
-        } else {
-            manufacturer_id.push(raw_bytes[1]);
-            manufacturer_id.push(raw_bytes[2]);
-            manufacturer_id.push(raw_bytes[3]);
+impl TryFrom<&[u8]> for SystemExclusive {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_status(raw_bytes, 0xF0)?;
+        let end = raw_bytes.len() - 1;
+        if end == 0 || raw_bytes[end] != 0xF7 {
+            return Err(ParseError::MissingEndOfExclusive {
+                offset: raw_bytes.len(),
+            });
+        }
+        if let Some(offset) = raw_bytes[1..end].iter().position(|&byte| byte > 127) {
+            return Err(ParseError::DataByteOutOfRange {
+                offset: offset + 1,
+                byte: raw_bytes[offset + 1],
+            });
+        }
+        let data = &raw_bytes[1..end];
+        let manufacturer_id_length = if data.first() == Some(&0) { 3 } else { 1 };
+        if data.len() < manufacturer_id_length {
+            return Err(ParseError::InvalidLength {
+                status: 0xF0,
+                expected: manufacturer_id_length + 1,
+                found: end,
+            });
         }
 
-        SystemExclusive {
+        Ok(SystemExclusive {
             bytes: raw_bytes.to_vec(),
-            manufacturer_id,
+            manufacturer_id: data[..manufacturer_id_length].to_vec(),
             category: Category::SystemCommon,
-        }
+        })
     }
 }
 
@@ -58,61 +155,131 @@ impl Default for SystemExclusive {
     }
 }
 
-#[derive(Debug)]
+/// The piece of a timecode which a quarter frame carries, in the order they are sent.
+///
+/// The low pieces carry the 4 lowest bits of their value and the high pieces the bits above
+/// them. The last piece also carries the frame rate in bits 1 and 2, 0 for 24, 1 for 25,
+/// 2 for 30 drop frame (29.97) and 3 for 30 frames per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QuarterFramePiece {
+    #[default]
+    FramesLow,
+    FramesHigh,
+    SecondsLow,
+    SecondsHigh,
+    MinutesLow,
+    MinutesHigh,
+    HoursLow,
+    HoursHighAndRate,
+}
+
+impl QuarterFramePiece {
+    pub const ALL: [QuarterFramePiece; 8] = [
+        QuarterFramePiece::FramesLow,
+        QuarterFramePiece::FramesHigh,
+        QuarterFramePiece::SecondsLow,
+        QuarterFramePiece::SecondsHigh,
+        QuarterFramePiece::MinutesLow,
+        QuarterFramePiece::MinutesHigh,
+        QuarterFramePiece::HoursLow,
+        QuarterFramePiece::HoursHighAndRate,
+    ];
+
+    /// Uses the 3 lowest bits of the number.
+    pub fn from_number(number: u8) -> Self {
+        Self::ALL[(number & 0b111) as usize]
+    }
+    pub fn number(&self) -> u8 {
+        *self as u8
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuarterFramePiece::FramesLow => "frames_low",
+            QuarterFramePiece::FramesHigh => "frames_high",
+            QuarterFramePiece::SecondsLow => "seconds_low",
+            QuarterFramePiece::SecondsHigh => "seconds_high",
+            QuarterFramePiece::MinutesLow => "minutes_low",
+            QuarterFramePiece::MinutesHigh => "minutes_high",
+            QuarterFramePiece::HoursLow => "hours_low",
+            QuarterFramePiece::HoursHighAndRate => "hours_high_and_rate",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|piece| piece.name() == name)
+    }
+    /// The piece which is sent after this one when playing forwards.
+    pub fn next(&self) -> Self {
+        Self::from_number(self.number() + 1)
+    }
+    /// The piece which is sent before this one when playing forwards.
+    pub fn previous(&self) -> Self {
+        Self::from_number(self.number() + 7)
+    }
+}
+
+/// A midi time code quarter frame message, which carries a nibble of a timecode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TimeCodeQuarterFrame {
     bytes: [u8; 2],
-    message_type: u8,
-    values: u8,
     pub category: Category,
 }
 
 impl TimeCodeQuarterFrame {
-    pub fn new(message_type: u64, values: u64) -> Self {
+    pub fn try_new(piece: u64, nibble: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("piece", piece, 7)?,
+            check_range("nibble", nibble, 15)?,
+        ))
+    }
+    /// The piece is given by its number.
+    pub fn new(piece: u64, nibble: u64) -> Self {
+        Self::from_piece(
+            QuarterFramePiece::from_number(piece.min(7) as u8),
+            nibble.min(15) as u8,
+        )
+    }
+    pub fn from_piece(piece: QuarterFramePiece, nibble: u8) -> Self {
         Self {
-            bytes: [
-                0xF1,
-                (message_type.min(7) << 4) as u8 | values.min(15) as u8,
-            ],
-            message_type: message_type.min(7) as u8,
-            values: values.min(15) as u8,
+            bytes: [0xF1, (piece.number() << 4) | nibble.min(15)],
             category: Category::SystemCommon,
         }
     }
-    pub fn message_type(&self) -> u8 {
-        self.message_type
+    pub fn piece(&self) -> QuarterFramePiece {
+        QuarterFramePiece::from_number(self.bytes[1] >> 4)
     }
-    pub fn values(&self) -> u8 {
-        self.values
+    pub fn nibble(&self) -> u8 {
+        self.bytes[1] & 0x0F
     }
 
-    pub fn change_message_type(&mut self, message_type: u8) {
-        self.message_type = message_type;
-        self.bytes[1] = (self.message_type.min(7) << 4) | self.values.min(15);
+    pub fn change_piece(&mut self, piece: QuarterFramePiece) {
+        *self = Self::from_piece(piece, self.nibble());
     }
-    pub fn change_values(&mut self, values: u8) {
-        self.values = values;
-        self.bytes[1] = (self.message_type.min(7) << 4) | self.values.min(15);
+    pub fn change_nibble(&mut self, nibble: u8) {
+        *self = Self::from_piece(self.piece(), nibble);
     }
 }
 
-impl From<&[u8]> for TimeCodeQuarterFrame {
-    fn from(raw_bytes: &[u8]) -> Self {
-        TimeCodeQuarterFrame {
+impl TryFrom<&[u8]> for TimeCodeQuarterFrame {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xF1, 2)?;
+        Ok(TimeCodeQuarterFrame {
             bytes: [raw_bytes[0], raw_bytes[1]],
-            message_type: (raw_bytes[1] & 0b0111_0000) >> 4,
-            values: raw_bytes[1] & 0b0000_1111,
             category: Category::SystemCommon,
-        }
+        })
     }
 }
 
 impl Default for TimeCodeQuarterFrame {
+    /// The low nibble of the frames, 0.
     fn default() -> Self {
-        todo!()
+        Self::from_piece(QuarterFramePiece::FramesLow, 0)
     }
 }
 
-#[derive(Debug)]
+/// A song position pointer message which counts midi beats (sixteenth notes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SongPosition {
     bytes: [u8; 3],
     midi_beats_elapsed: u16,
@@ -120,6 +287,9 @@ pub struct SongPosition {
 }
 
 impl SongPosition {
+    pub fn try_new(midi_beats_elapsed: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("midi_beats_elapsed", midi_beats_elapsed, 16383)?))
+    }
     pub fn new(midi_beats_elapsed: u64) -> Self {
         let midi_beats_elapsed = midi_beats_elapsed.min(16383) as u16;
         let msb = ((midi_beats_elapsed >> 7) as u8) & 0b0111_1111;
@@ -135,21 +305,24 @@ impl SongPosition {
     }
     pub fn change_midi_beats_elapsed(&mut self, midi_beats_elapsed: u16) {
         self.midi_beats_elapsed = midi_beats_elapsed.min(16383);
-        let msb = ((midi_beats_elapsed >> 7) as u8) & 0b0111_1111;
-        let lsb = (midi_beats_elapsed as u8) & 0b0111_1111;
+        let msb = ((self.midi_beats_elapsed >> 7) as u8) & 0b0111_1111;
+        let lsb = (self.midi_beats_elapsed as u8) & 0b0111_1111;
         self.bytes[1] = lsb;
         self.bytes[2] = msb;
     }
 }
 
-impl From<&[u8]> for SongPosition {
-    fn from(raw_bytes: &[u8]) -> Self {
+impl TryFrom<&[u8]> for SongPosition {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xF2, 3)?;
         let midi_beats_elapsed = ((raw_bytes[2] as u16) << 7) | raw_bytes[1] as u16;
-        SongPosition {
+        Ok(SongPosition {
             bytes: [raw_bytes[0], raw_bytes[1], raw_bytes[2]],
             midi_beats_elapsed,
             category: Category::SystemCommon,
-        }
+        })
     }
 }
 
@@ -163,7 +336,8 @@ impl Default for SongPosition {
     }
 }
 
-#[derive(Debug)]
+/// A song select message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SongSelect {
     bytes: [u8; 2],
     number: u8,
@@ -171,6 +345,9 @@ pub struct SongSelect {
 }
 
 impl SongSelect {
+    pub fn try_new(number: u64) -> Result<Self, RangeError> {
+        Ok(Self::new(check_range("number", number, 127)?))
+    }
     pub fn new(number: u64) -> Self {
         Self {
             bytes: [0xF3, number.min(127) as u8],
@@ -181,15 +358,22 @@ impl SongSelect {
     pub fn number(&self) -> u8 {
         self.number
     }
+    pub fn change_number(&mut self, number: u8) {
+        self.number = number.min(127);
+        self.bytes[1] = self.number;
+    }
 }
 
-impl From<&[u8]> for SongSelect {
-    fn from(raw_bytes: &[u8]) -> Self {
-        SongSelect {
+impl TryFrom<&[u8]> for SongSelect {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xF3, 2)?;
+        Ok(SongSelect {
             bytes: [raw_bytes[0], raw_bytes[1]],
             number: raw_bytes[1],
             category: Category::SystemCommon,
-        }
+        })
     }
 }
 
@@ -203,7 +387,8 @@ impl Default for SongSelect {
     }
 }
 
-#[derive(Debug)]
+/// A tune request message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TuneRequest {
     bytes: [u8; 1],
     pub category: Category,
@@ -224,16 +409,20 @@ impl TuneRequest {
     }
 }
 
-impl From<&[u8]> for TuneRequest {
-    fn from(raw_bytes: &[u8]) -> Self {
-        TuneRequest {
+impl TryFrom<&[u8]> for TuneRequest {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xF6, 1)?;
+        Ok(TuneRequest {
             bytes: [raw_bytes[0]],
             category: Category::SystemCommon,
-        }
+        })
     }
 }
 
-#[derive(Debug)]
+/// A standalone end of exclusive message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct EndOfExclusive {
     bytes: [u8; 1],
     pub category: Category,
@@ -254,12 +443,15 @@ impl EndOfExclusive {
     }
 }
 
-impl From<&[u8]> for EndOfExclusive {
-    fn from(raw_bytes: &[u8]) -> Self {
-        EndOfExclusive {
+impl TryFrom<&[u8]> for EndOfExclusive {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xF7, 1)?;
+        Ok(EndOfExclusive {
             bytes: [raw_bytes[0]],
             category: Category::SystemCommon,
-        }
+        })
     }
 }
 
@@ -1,8 +1,11 @@
 use crate::impl_midi_message;
+use crate::check_message;
 use crate::Category;
 use crate::MidiMessage;
+use crate::ParseError;
 
-#[derive(Debug)]
+/// A timing clock message, sent 24 times per quarter note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TimingClock {
     bytes: [u8; 1],
     pub category: Category,
@@ -23,16 +26,20 @@ impl TimingClock {
     }
 }
 
-impl From<&[u8]> for TimingClock {
-    fn from(raw_bytes: &[u8]) -> Self {
-        TimingClock {
+impl TryFrom<&[u8]> for TimingClock {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xF8, 1)?;
+        Ok(TimingClock {
             bytes: [raw_bytes[0]],
             category: Category::SystemRealtime,
-        }
+        })
     }
 }
 
-#[derive(Debug)]
+/// A start message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Start {
     bytes: [u8; 1],
     pub category: Category,
@@ -53,16 +60,20 @@ impl Start {
     }
 }
 
-impl From<&[u8]> for Start {
-    fn from(raw_bytes: &[u8]) -> Self {
-        Start {
+impl TryFrom<&[u8]> for Start {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xFA, 1)?;
+        Ok(Start {
             bytes: [raw_bytes[0]],
             category: Category::SystemRealtime,
-        }
+        })
     }
 }
 
-#[derive(Debug)]
+/// A continue message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Continue {
     bytes: [u8; 1],
     pub category: Category,
@@ -83,16 +94,20 @@ impl Continue {
     }
 }
 
-impl From<&[u8]> for Continue {
-    fn from(raw_bytes: &[u8]) -> Self {
-        Continue {
+impl TryFrom<&[u8]> for Continue {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xFB, 1)?;
+        Ok(Continue {
             bytes: [raw_bytes[0]],
             category: Category::SystemRealtime,
-        }
+        })
     }
 }
 
-#[derive(Debug)]
+/// A stop message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Stop {
     bytes: [u8; 1],
     pub category: Category,
@@ -113,16 +128,20 @@ impl Stop {
     }
 }
 
-impl From<&[u8]> for Stop {
-    fn from(raw_bytes: &[u8]) -> Self {
-        Stop {
+impl TryFrom<&[u8]> for Stop {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xFC, 1)?;
+        Ok(Stop {
             bytes: [raw_bytes[0]],
             category: Category::SystemRealtime,
-        }
+        })
     }
 }
 
-#[derive(Debug)]
+/// An active sensing message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ActiveSensing {
     bytes: [u8; 1],
     pub category: Category,
@@ -143,16 +162,20 @@ impl ActiveSensing {
     }
 }
 
-impl From<&[u8]> for ActiveSensing {
-    fn from(raw_bytes: &[u8]) -> Self {
-        ActiveSensing {
+impl TryFrom<&[u8]> for ActiveSensing {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xFE, 1)?;
+        Ok(ActiveSensing {
             bytes: [raw_bytes[0]],
             category: Category::SystemRealtime,
-        }
+        })
     }
 }
 
-#[derive(Debug)]
+/// A system reset message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Reset {
     bytes: [u8; 1],
     pub category: Category,
@@ -173,12 +196,15 @@ impl Reset {
     }
 }
 
-impl From<&[u8]> for Reset {
-    fn from(raw_bytes: &[u8]) -> Self {
-        Reset {
+impl TryFrom<&[u8]> for Reset {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &[u8]) -> Result<Self, Self::Error> {
+        check_message(raw_bytes, 0xFF, 1)?;
+        Ok(Reset {
             bytes: [raw_bytes[0]],
             category: Category::SystemRealtime,
-        }
+        })
     }
 }
 
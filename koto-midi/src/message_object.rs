@@ -0,0 +1,833 @@
+use crate::collect_list_of_midi_bytes_as_u8;
+use crate::collect_list_of_u8;
+use crate::manufacturer;
+use crate::message::*;
+use crate::sysex_module::koto_sysex_detail;
+
+use koto::derive::*;
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+/// The names of all the fields which messages can have.
+const FIELD_NAMES: &[&str] = &[
+    "note",
+    "controller",
+    "velocity",
+    "pressure",
+    "value",
+    "program",
+    "channel",
+    "bend_amount",
+    "manufacturer_id",
+    "message_content",
+    "piece",
+    "nibble",
+    "midi_beats_elapsed",
+    "number",
+    "text",
+    "microseconds_per_quarter_note",
+    "hours",
+    "minutes",
+    "seconds",
+    "frames",
+    "fractional_frames",
+    "rate",
+    "numerator",
+    "denominator",
+    "clocks_per_click",
+    "thirty_seconds_per_quarter_note",
+    "sharps",
+    "flats",
+    "minor",
+];
+
+pub fn category_literal(category: Category) -> &'static str {
+    match category {
+        Category::ChannelVoice => "channel_voice",
+        Category::ChannelMode => "channel_mode",
+        Category::SystemCommon => "system_common",
+        Category::SystemRealtime => "system_realtime",
+        Category::Meta => "meta",
+        Category::Unknown => "unknown",
+    }
+}
+
+pub fn type_literal(message: &Message) -> &'static str {
+    match message {
+        Message::NoteOff(_) => "note_off",
+        Message::NoteOn(_) => "note_on",
+        Message::PolyAfterTouch(_) => "poly_after_touch",
+        Message::ControlChange(_) => "control_change",
+        Message::ProgramChange(_) => "program_change",
+        Message::AfterTouch(_) => "after_touch",
+        Message::PitchBend(_) => "pitch_bend",
+        Message::AllSoundOff(_) => "all_sound_off",
+        Message::ResetAllControllers(_) => "reset_all_controllers",
+        Message::LocalControl(_) => "local_control",
+        Message::AllNotesOff(_) => "all_notes_off",
+        Message::OmniModeOff(_) => "omni_mode_off",
+        Message::OmniModeOn(_) => "omni_mode_on",
+        Message::MonoModeOn(_) => "mono_mode_on",
+        Message::PolyModeOn(_) => "poly_mode_on",
+        Message::SystemExclusive(_) => "system_exclusive",
+        Message::TimeCodeQuarterFrame(_) => "time_code_quarter_frame",
+        Message::SongPosition(_) => "song_position",
+        Message::SongSelect(_) => "song_select",
+        Message::TuneRequest(_) => "tune_request",
+        Message::EndOfExclusive(_) => "end_of_exclusive",
+        Message::TimingClock(_) => "timing_clock",
+        Message::Start(_) => "start",
+        Message::Continue(_) => "continue",
+        Message::Stop(_) => "stop",
+        Message::ActiveSensing(_) => "active_sensing",
+        Message::Reset(_) => "reset",
+        Message::Text(_) => "text",
+        Message::Copyright(_) => "copyright",
+        Message::TrackName(_) => "track_name",
+        Message::InstrumentName(_) => "instrument_name",
+        Message::Lyric(_) => "lyric",
+        Message::Marker(_) => "marker",
+        Message::CuePoint(_) => "cue_point",
+        Message::EndOfTrack(_) => "end_of_track",
+        Message::Tempo(_) => "tempo",
+        Message::SmpteOffset(_) => "smpte_offset",
+        Message::TimeSignature(_) => "time_signature",
+        Message::KeySignature(_) => "key_signature",
+        Message::SequencerSpecific(_) => "sequencer_specific",
+        Message::Undefined => "undefined",
+        Message::Malformed(_) => "malformed",
+    }
+}
+
+/// Returns the fields of a message in the order its constructor takes them.
+fn fields(message: &Message) -> Vec<(&'static str, KValue)> {
+    match message {
+        Message::NoteOff(m) => vec![
+            ("note", m.note().into()),
+            ("velocity", m.velocity().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::NoteOn(m) => vec![
+            ("note", m.note().into()),
+            ("velocity", m.velocity().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::PolyAfterTouch(m) => vec![
+            ("note", m.note().into()),
+            ("pressure", m.pressure().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::ControlChange(m) => vec![
+            ("controller", m.controller().into()),
+            ("value", m.value().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::ProgramChange(m) => vec![
+            ("program", m.program().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::AfterTouch(m) => vec![
+            ("pressure", m.pressure().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::PitchBend(m) => vec![
+            ("bend_amount", m.bend_amount().into()),
+            ("channel", m.channel().into()),
+        ],
+        Message::AllSoundOff(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::ResetAllControllers(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::LocalControl(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::AllNotesOff(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::OmniModeOff(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::OmniModeOn(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::MonoModeOn(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::PolyModeOn(m) => channel_mode_fields(m.value(), m.channel(), m.pack()),
+        Message::SystemExclusive(m) => vec![
+            ("manufacturer_id", byte_list(m.manufacturer_id())),
+            ("message_content", byte_list(m.message_content())),
+        ],
+        Message::TimeCodeQuarterFrame(m) => vec![
+            ("piece", m.piece().name().into()),
+            ("nibble", m.nibble().into()),
+        ],
+        Message::SongPosition(m) => vec![("midi_beats_elapsed", m.midi_beats_elapsed().into())],
+        Message::SongSelect(m) => vec![("number", m.number().into())],
+        Message::Text(m) => vec![("text", m.text().as_str().into())],
+        Message::Copyright(m) => vec![("text", m.text().as_str().into())],
+        Message::TrackName(m) => vec![("text", m.text().as_str().into())],
+        Message::InstrumentName(m) => vec![("text", m.text().as_str().into())],
+        Message::Lyric(m) => vec![("text", m.text().as_str().into())],
+        Message::Marker(m) => vec![("text", m.text().as_str().into())],
+        Message::CuePoint(m) => vec![("text", m.text().as_str().into())],
+        Message::Tempo(m) => vec![(
+            "microseconds_per_quarter_note",
+            m.microseconds_per_quarter_note().into(),
+        )],
+        Message::SmpteOffset(m) => vec![
+            ("hours", m.hours().into()),
+            ("minutes", m.minutes().into()),
+            ("seconds", m.seconds().into()),
+            ("frames", m.frames().into()),
+            ("fractional_frames", m.fractional_frames().into()),
+            ("rate", m.rate().into()),
+        ],
+        Message::TimeSignature(m) => vec![
+            ("numerator", m.numerator().into()),
+            ("denominator", m.denominator().into()),
+            ("clocks_per_click", m.clocks_per_click().into()),
+            (
+                "thirty_seconds_per_quarter_note",
+                m.thirty_seconds_per_quarter_note().into(),
+            ),
+        ],
+        Message::KeySignature(m) => vec![
+            ("sharps", m.sharps().into()),
+            ("flats", m.flats().into()),
+            ("minor", (m.is_minor() as u8).into()),
+        ],
+        Message::SequencerSpecific(m) => vec![
+            ("manufacturer_id", byte_list(m.manufacturer_id())),
+            ("message_content", byte_list(m.message_content())),
+        ],
+        _ => vec![],
+    }
+}
+
+/// Channel mode messages also expose their controller number as `note`, it can't be changed.
+fn channel_mode_fields(value: u8, channel: u8, bytes: &[u8]) -> Vec<(&'static str, KValue)> {
+    vec![
+        ("value", value.into()),
+        ("channel", channel.into()),
+        ("note", bytes[1].into()),
+    ]
+}
+
+pub(crate) fn byte_list(bytes: &[u8]) -> KValue {
+    let bytes = bytes.iter().map(|&byte| byte.into()).collect::<Vec<KValue>>();
+    KValue::List(KList::from_slice(&bytes[..]))
+}
+
+fn manufacturer_name(message: &Message) -> Option<&'static str> {
+    match message {
+        Message::SystemExclusive(m) => m.manufacturer_name(),
+        Message::SequencerSpecific(m) => manufacturer::name(m.manufacturer_id()),
+        _ => None,
+    }
+}
+
+/// Checks the length of a manufacturer id, that extended ids start with 0 and that its bytes are
+/// data bytes, in strict and lenient modes alike, since other bytes would end the message.
+pub(crate) fn check_manufacturer_id(id: &[u8], context: &str) -> Result<(), RuntimeError> {
+    match manufacturer::validate(id) {
+        Ok(()) => Ok(()),
+        Err(error) => runtime_error!("{context}: {error}"),
+    }
+}
+
+/// Checks that the content of a system exclusive message only has data bytes, in strict and
+/// lenient modes alike, since other bytes would end the message when it is sent.
+pub(crate) fn check_sysex_content(content: &[u8], context: &str) -> Result<(), RuntimeError> {
+    for &byte in content {
+        if let Err(error) = check_range("message_content", byte as u64, 127) {
+            return runtime_error!(
+                "{context}: {error}, 8 bit data can be encoded with midi.sysex.pack7 or \
+                 midi.sysex.nibblize"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The current name of a field which was renamed.
+///
+/// The controller number of control change messages used to be called `note`, it can still be
+/// read and written with that name.
+fn field_alias<'a>(message: &Message, name: &'a str) -> &'a str {
+    match (message, name) {
+        (Message::ControlChange(_), "note") => "controller",
+        _ => name,
+    }
+}
+
+/// The largest value a numeric field can have.
+fn field_max(name: &str) -> Option<u64> {
+    match name {
+        "note" | "controller" | "velocity" | "pressure" | "value" | "program" | "number" => {
+            Some(127)
+        }
+        "channel" | "nibble" => Some(15),
+        "bend_amount" | "midi_beats_elapsed" => Some(16383),
+        "piece" => Some(7),
+        "microseconds_per_quarter_note" => Some(0xFFFFFF),
+        "hours" => Some(23),
+        "minutes" | "seconds" => Some(59),
+        "frames" => Some(29),
+        "fractional_frames" => Some(99),
+        "rate" => Some(3),
+        "numerator" | "clocks_per_click" | "thirty_seconds_per_quarter_note" => Some(255),
+        "denominator" => Some(128),
+        "sharps" | "flats" => Some(7),
+        "minor" => Some(1),
+        _ => None,
+    }
+}
+
+/// Changes a field of a message with the setter of its type.
+///
+/// Returns false when the message doesn't have a field with the given name which can be changed.
+fn change_field(message: &mut Message, name: &str, value: u64) -> bool {
+    let byte = value.min(u8::MAX as u64) as u8;
+    let word = value.min(u16::MAX as u64) as u16;
+    let long = value.min(u32::MAX as u64) as u32;
+    match (message, name) {
+        (Message::NoteOff(m), "note") => m.change_note(byte),
+        (Message::NoteOff(m), "velocity") => m.change_velocity(byte),
+        (Message::NoteOff(m), "channel") => m.change_channel(byte),
+        (Message::NoteOn(m), "note") => m.change_note(byte),
+        (Message::NoteOn(m), "velocity") => m.change_velocity(byte),
+        (Message::NoteOn(m), "channel") => m.change_channel(byte),
+        (Message::PolyAfterTouch(m), "note") => m.change_note(byte),
+        (Message::PolyAfterTouch(m), "pressure") => m.change_pressure(byte),
+        (Message::PolyAfterTouch(m), "channel") => m.change_channel(byte),
+        (Message::ControlChange(m), "controller") => m.change_controller(byte),
+        (Message::ControlChange(m), "value") => m.change_value(byte),
+        (Message::ControlChange(m), "channel") => m.change_channel(byte),
+        (Message::ProgramChange(m), "program") => m.change_program(byte),
+        (Message::ProgramChange(m), "channel") => m.change_channel(byte),
+        (Message::AfterTouch(m), "pressure") => m.change_pressure(byte),
+        (Message::AfterTouch(m), "channel") => m.change_channel(byte),
+        (Message::PitchBend(m), "bend_amount") => m.change_bend_amount(word),
+        (Message::PitchBend(m), "channel") => m.change_channel(byte),
+        (Message::AllSoundOff(m), "value") => m.change_value(byte),
+        (Message::AllSoundOff(m), "channel") => m.change_channel(byte),
+        (Message::ResetAllControllers(m), "value") => m.change_value(byte),
+        (Message::ResetAllControllers(m), "channel") => m.change_channel(byte),
+        (Message::LocalControl(m), "value") => m.change_value(byte),
+        (Message::LocalControl(m), "channel") => m.change_channel(byte),
+        (Message::AllNotesOff(m), "value") => m.change_value(byte),
+        (Message::AllNotesOff(m), "channel") => m.change_channel(byte),
+        (Message::OmniModeOff(m), "value") => m.change_value(byte),
+        (Message::OmniModeOff(m), "channel") => m.change_channel(byte),
+        (Message::OmniModeOn(m), "value") => m.change_value(byte),
+        (Message::OmniModeOn(m), "channel") => m.change_channel(byte),
+        (Message::MonoModeOn(m), "value") => m.change_value(byte),
+        (Message::MonoModeOn(m), "channel") => m.change_channel(byte),
+        (Message::PolyModeOn(m), "value") => m.change_value(byte),
+        (Message::PolyModeOn(m), "channel") => m.change_channel(byte),
+        (Message::TimeCodeQuarterFrame(m), "piece") => {
+            m.change_piece(QuarterFramePiece::from_number(byte.min(7)))
+        }
+        (Message::TimeCodeQuarterFrame(m), "nibble") => m.change_nibble(byte),
+        (Message::SongPosition(m), "midi_beats_elapsed") => m.change_midi_beats_elapsed(word),
+        (Message::SongSelect(m), "number") => m.change_number(byte),
+        (Message::Tempo(m), "microseconds_per_quarter_note") => {
+            m.change_microseconds_per_quarter_note(long)
+        }
+        (Message::SmpteOffset(m), "hours") => m.change_hours(byte),
+        (Message::SmpteOffset(m), "minutes") => m.change_minutes(byte),
+        (Message::SmpteOffset(m), "seconds") => m.change_seconds(byte),
+        (Message::SmpteOffset(m), "frames") => m.change_frames(byte),
+        (Message::SmpteOffset(m), "fractional_frames") => m.change_fractional_frames(byte),
+        (Message::SmpteOffset(m), "rate") => m.change_rate(byte),
+        (Message::TimeSignature(m), "numerator") => m.change_numerator(byte),
+        (Message::TimeSignature(m), "denominator") => m.change_denominator(byte),
+        (Message::TimeSignature(m), "clocks_per_click") => m.change_clocks_per_click(byte),
+        (Message::TimeSignature(m), "thirty_seconds_per_quarter_note") => {
+            m.change_thirty_seconds_per_quarter_note(byte)
+        }
+        (Message::KeySignature(m), "sharps") => m.change_sharps(byte),
+        (Message::KeySignature(m), "flats") => m.change_flats(byte),
+        (Message::KeySignature(m), "minor") => m.change_minor(value > 0),
+        _ => return false,
+    }
+    true
+}
+
+/// Changes the text of a meta event.
+///
+/// Returns false when the message doesn't have a text.
+fn change_text(message: &mut Message, text: &str) -> bool {
+    match message {
+        Message::Text(m) => m.change_text(text),
+        Message::Copyright(m) => m.change_text(text),
+        Message::TrackName(m) => m.change_text(text),
+        Message::InstrumentName(m) => m.change_text(text),
+        Message::Lyric(m) => m.change_text(text),
+        Message::Marker(m) => m.change_text(text),
+        Message::CuePoint(m) => m.change_text(text),
+        _ => return false,
+    }
+    true
+}
+
+/// Changes a field which is a list of bytes.
+///
+/// Returns false when the message doesn't have a field with the given name which is a list of bytes.
+fn change_bytes(message: &mut Message, name: &str, bytes: &[u8]) -> bool {
+    match (message, name) {
+        (Message::SystemExclusive(m), "manufacturer_id") => m.change_manufacturer_id(bytes),
+        (Message::SystemExclusive(m), "message_content") => m.change_message_content(bytes),
+        (Message::SequencerSpecific(m), "manufacturer_id") => m.change_manufacturer_id(bytes),
+        (Message::SequencerSpecific(m), "message_content") => m.change_message_content(bytes),
+        _ => return false,
+    }
+    true
+}
+
+/// Returns the default message of the given type.
+///
+/// Undefined and malformed messages don't have defaults.
+fn default_message(type_literal: &str) -> Option<Message> {
+    let message = match type_literal {
+        "note_off" => NoteOff::default().into(),
+        "note_on" => NoteOn::default().into(),
+        "poly_after_touch" => PolyAfterTouch::default().into(),
+        "control_change" => ControlChange::default().into(),
+        "program_change" => ProgramChange::default().into(),
+        "after_touch" => AfterTouch::default().into(),
+        "pitch_bend" => PitchBend::default().into(),
+        "all_sound_off" => AllSoundOff::default().into(),
+        "reset_all_controllers" => ResetAllControllers::default().into(),
+        "local_control" => LocalControl::default().into(),
+        "all_notes_off" => AllNotesOff::default().into(),
+        "omni_mode_off" => OmniModeOff::default().into(),
+        "omni_mode_on" => OmniModeOn::default().into(),
+        "mono_mode_on" => MonoModeOn::default().into(),
+        "poly_mode_on" => PolyModeOn::default().into(),
+        "system_exclusive" => SystemExclusive::default().into(),
+        "time_code_quarter_frame" => TimeCodeQuarterFrame::default().into(),
+        "song_position" => SongPosition::default().into(),
+        "song_select" => SongSelect::default().into(),
+        "tune_request" => TuneRequest::default().into(),
+        "end_of_exclusive" => EndOfExclusive::default().into(),
+        "timing_clock" => TimingClock::default().into(),
+        "start" => Start::default().into(),
+        "continue" => Continue::default().into(),
+        "stop" => Stop::default().into(),
+        "active_sensing" => ActiveSensing::default().into(),
+        "reset" => Reset::default().into(),
+        "text" => Text::default().into(),
+        "copyright" => Copyright::default().into(),
+        "track_name" => TrackName::default().into(),
+        "instrument_name" => InstrumentName::default().into(),
+        "lyric" => Lyric::default().into(),
+        "marker" => Marker::default().into(),
+        "cue_point" => CuePoint::default().into(),
+        "end_of_track" => EndOfTrack::default().into(),
+        "tempo" => Tempo::default().into(),
+        "smpte_offset" => SmpteOffset::default().into(),
+        "time_signature" => TimeSignature::default().into(),
+        "key_signature" => KeySignature::default().into(),
+        "sequencer_specific" => SequencerSpecific::default().into(),
+        _ => return None,
+    };
+    Some(message)
+}
+
+/// Builds a message of the given type from a map of its fields.
+///
+/// Missing fields take their default values. Entries which match the message which is being built
+/// are accepted even if they can't be set, so maps which `to_map` returns can be rebuilt.
+pub(crate) fn message_from_map(
+    type_literal: &str,
+    fields: &KMap,
+    strict: bool,
+) -> Result<KotoMessage, RuntimeError> {
+    let Some(message) = default_message(type_literal) else {
+        return runtime_error!("{type_literal} messages can't be built from a map");
+    };
+    let mut message = KotoMessage::new(message).with_strict(strict);
+    let current = message.to_map();
+    for (key, value) in fields.data().iter() {
+        let name = match key.value() {
+            KValue::Str(name) => name.as_str(),
+            unexpected => return type_error("a field name", unexpected),
+        };
+        let unchanged = match (current.get(name), value) {
+            (Some(KValue::Number(a)), KValue::Number(b)) => a == *b,
+            (Some(KValue::Str(a)), KValue::Str(b)) => a == *b,
+            _ => false,
+        };
+        // The offset of a parsed message, and the manufacturer name and detail of a system
+        // exclusive message aren't fields of the message.
+        if unchanged || matches!(name, "offset" | "manufacturer_name" | "detail") {
+            continue;
+        }
+        message.set_field(name, value)?;
+    }
+    Ok(message)
+}
+
+/// A midi message which can be used from koto scripts.
+///
+/// Fields are read from the bytes of the message and setting a field re-encodes the bytes,
+/// so the fields and the packed bytes of a message always agree.
+#[derive(Clone, KotoType, KotoCopy)]
+#[koto(type_name = "Message")]
+pub struct KotoMessage {
+    message: Message,
+    /// System realtime messages which were interleaved with the bytes of a parsed message.
+    realtime: Vec<Message>,
+    /// The number of realtime messages which arrived before the message was complete.
+    realtime_before_message: usize,
+    /// The position of the message in a parsed buffer.
+    offset: Option<usize>,
+    /// Setters throw for out of range values instead of clamping them.
+    strict: bool,
+}
+
+impl KotoMessage {
+    pub fn new(message: Message) -> Self {
+        Self {
+            message,
+            realtime: vec![],
+            realtime_before_message: 0,
+            offset: None,
+            strict: false,
+        }
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    pub fn with_realtime(mut self, realtime: Vec<Message>, realtime_before_message: usize) -> Self {
+        self.realtime = realtime;
+        self.realtime_before_message = realtime_before_message;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn type_literal(&self) -> &'static str {
+        type_literal(&self.message)
+    }
+
+    fn field(&self, name: &str) -> Result<KValue, RuntimeError> {
+        let name = field_alias(&self.message, name);
+        match fields(&self.message).into_iter().find(|(field, _)| *field == name) {
+            Some((_, value)) => Ok(value),
+            None => runtime_error!(
+                "'{name}' is not a field of {} messages",
+                self.type_literal()
+            ),
+        }
+    }
+
+    fn set_field(&mut self, name: &str, value: &KValue) -> Result<(), RuntimeError> {
+        let name = field_alias(&self.message, name);
+        let Some(&name) = FIELD_NAMES.iter().find(|field| **field == name) else {
+            return runtime_error!(
+                "'{name}' is not a field of {} messages",
+                self.type_literal()
+            );
+        };
+        let changed = match (&mut self.message, value) {
+            (_, KValue::Number(KNumber::I64(value))) if *value >= 0 => {
+                let value = *value as u64;
+                if self.strict {
+                    if let Some(max) = field_max(name) {
+                        let checked = if name == "denominator" {
+                            check_power_of_two(name, value, max)
+                        } else {
+                            check_range(name, value, max)
+                        };
+                        if let Err(error) = checked {
+                            return runtime_error!("{error}");
+                        }
+                    }
+                }
+                change_field(&mut self.message, name, value)
+            }
+            (_, KValue::List(bytes)) if matches!(name, "manufacturer_id" | "message_content") => {
+                let error_literal = if name == "manufacturer_id" {
+                    "manufacturer_id requires a list with single or 3 bytes"
+                } else {
+                    "message_content requires a list of bytes"
+                };
+                // The data of sequencer specific meta events isn't sent to devices.
+                let bytes = if self.strict && !self.message.is_meta() {
+                    collect_list_of_midi_bytes_as_u8(bytes, error_literal)?
+                } else {
+                    collect_list_of_u8(bytes, error_literal)?
+                };
+                if name == "manufacturer_id" {
+                    check_manufacturer_id(&bytes, name)?;
+                } else if matches!(self.message, Message::SystemExclusive(_)) {
+                    check_sysex_content(&bytes, name)?;
+                }
+                change_bytes(&mut self.message, name, &bytes[..])
+            }
+            (_, KValue::Str(text)) if name == "text" => change_text(&mut self.message, text),
+            (Message::TimeCodeQuarterFrame(m), KValue::Str(piece)) if name == "piece" => {
+                match QuarterFramePiece::from_name(piece) {
+                    Some(piece) => m.change_piece(piece),
+                    None => {
+                        return runtime_error!(
+                            "piece must be a number from 0 to 7 or one of {}",
+                            QuarterFramePiece::ALL.map(|piece| piece.name()).join(", ")
+                        )
+                    }
+                }
+                true
+            }
+            (_, unexpected) => return type_error("a positive integer", unexpected),
+        };
+        if changed {
+            Ok(())
+        } else {
+            runtime_error!(
+                "'{name}' can't be set on {} messages",
+                self.type_literal()
+            )
+        }
+    }
+
+    /// Returns a copy of the message with the fields in the map changed.
+    fn with(&self, fields: &KMap) -> Result<KotoMessage, RuntimeError> {
+        let mut message = self.clone();
+        for (key, value) in fields.data().iter() {
+            match key.value() {
+                KValue::Str(name) => message.set_field(name.as_str(), value)?,
+                unexpected => return type_error("a field name", unexpected),
+            }
+        }
+        Ok(message)
+    }
+
+    fn to_map(&self) -> KMap {
+        let map = KMap::new();
+        map.insert("type", self.type_literal());
+        map.insert("category", category_literal(self.message.category()));
+        for (name, value) in fields(&self.message) {
+            map.insert(name, value);
+        }
+        if let Message::Malformed(error) = &self.message {
+            map.insert("reason", error.reason());
+        }
+        if let offset @ KValue::Number(_) = self.offset() {
+            map.insert("offset", offset);
+        }
+        if let Some(name) = manufacturer_name(&self.message) {
+            map.insert("manufacturer_name", name);
+        }
+        if let detail @ KValue::Map(_) = self.detail() {
+            map.insert("detail", detail);
+        }
+        map
+    }
+
+    /// The fields of a system exclusive message which the library knows how to read.
+    fn detail(&self) -> KValue {
+        match &self.message {
+            Message::SystemExclusive(message) => match koto_sysex_detail(message) {
+                Some(detail) => detail.into(),
+                None => KValue::Null,
+            },
+            _ => KValue::Null,
+        }
+    }
+
+    fn reason(&self) -> KValue {
+        match &self.message {
+            Message::Malformed(error) => error.reason().into(),
+            _ => KValue::Null,
+        }
+    }
+
+    fn offset(&self) -> KValue {
+        match (&self.message, self.offset) {
+            // Malformed messages point to the byte which caused the error.
+            (Message::Malformed(error), Some(offset)) => (offset + error.offset()).into(),
+            (Message::Malformed(error), None) => error.offset().into(),
+            (_, Some(offset)) => offset.into(),
+            (_, None) => KValue::Null,
+        }
+    }
+
+    fn realtime(&self) -> KValue {
+        let realtime = self
+            .realtime
+            .iter()
+            .map(|message| KotoMessage::new(message.clone()).into())
+            .collect::<Vec<KValue>>();
+        KValue::List(KList::from_slice(&realtime[..]))
+    }
+
+    /// The message and its interleaved realtime messages in the order they were completed.
+    fn messages(&self) -> KValue {
+        let (before, after) = self.realtime.split_at(self.realtime_before_message);
+        let messages = before
+            .iter()
+            .map(|message| KotoMessage::new(message.clone()).into())
+            .chain(std::iter::once(
+                KotoMessage::new(self.message.clone())
+                    .with_strict(self.strict)
+                    .into(),
+            ))
+            .chain(after.iter().map(|message| KotoMessage::new(message.clone()).into()))
+            .collect::<Vec<KValue>>();
+        KValue::List(KList::from_slice(&messages[..]))
+    }
+}
+
+impl From<Message> for KotoMessage {
+    fn from(message: Message) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<KotoMessage> for KValue {
+    fn from(message: KotoMessage) -> Self {
+        KValue::Object(message.into())
+    }
+}
+
+/// Adds a method which borrows the message it is called on.
+fn add_method(
+    entries: &KMap,
+    name: &str,
+    f: impl Fn(&KotoMessage, &[KValue]) -> Result<KValue, RuntimeError> + KotoSend + KotoSync + 'static,
+) {
+    entries.add_fn(name, move |ctx| {
+        match ctx.instance_and_args(
+            |instance| matches!(instance, KValue::Object(_)),
+            KotoMessage::type_static(),
+        )? {
+            (KValue::Object(object), args) => f(&*object.cast::<KotoMessage>()?, args),
+            (_, unexpected) => type_error_with_slice(KotoMessage::type_static(), unexpected),
+        }
+    });
+}
+
+/// Adds a method which changes the message it is called on and returns it.
+fn add_mut_method(
+    entries: &KMap,
+    name: &str,
+    f: impl Fn(&mut KotoMessage, &[KValue]) -> Result<(), RuntimeError> + KotoSend + KotoSync + 'static,
+) {
+    entries.add_fn(name, move |ctx| {
+        match ctx.instance_and_args(
+            |instance| matches!(instance, KValue::Object(_)),
+            KotoMessage::type_static(),
+        )? {
+            (KValue::Object(object), args) => {
+                f(&mut *object.cast_mut::<KotoMessage>()?, args)?;
+                Ok(KValue::Object(object.clone()))
+            }
+            (_, unexpected) => type_error_with_slice(KotoMessage::type_static(), unexpected),
+        }
+    });
+}
+
+fn make_entries() -> KMap {
+    let entries = KMap::new();
+
+    add_method(&entries, "type", |message, _| Ok(message.type_literal().into()));
+    add_method(&entries, "category", |message, _| {
+        Ok(category_literal(message.message.category()).into())
+    });
+    add_method(&entries, "pack", |message, _| Ok(byte_list(message.message.pack())));
+    add_method(&entries, "to_map", |message, _| Ok(message.to_map().into()));
+    add_method(&entries, "reason", |message, _| Ok(message.reason()));
+    add_method(&entries, "offset", |message, _| Ok(message.offset()));
+    add_method(&entries, "realtime", |message, _| Ok(message.realtime()));
+    add_method(&entries, "messages", |message, _| Ok(message.messages()));
+    add_method(&entries, "detail", |message, _| Ok(message.detail()));
+    add_method(&entries, "manufacturer_name", |message, _| {
+        Ok(manufacturer_name(&message.message).map_or(KValue::Null, KValue::from))
+    });
+
+    add_method(&entries, "with", |message, args| match args {
+        [KValue::Map(fields)] => message.with(fields).map(KValue::from),
+        _ => runtime_error!("with requires a single map of fields as its argument"),
+    });
+
+    for name in FIELD_NAMES {
+        add_method(&entries, name, move |message, _| message.field(name));
+        add_mut_method(&entries, &format!("set_{name}"), move |message, args| match args {
+            [value] => message.set_field(name, value),
+            _ => runtime_error!("set_{name} requires a single value as its argument"),
+        });
+    }
+
+    entries
+}
+
+thread_local! {
+    static MESSAGE_ENTRIES: KMap = make_entries();
+}
+
+impl KotoEntries for KotoMessage {
+    fn entries(&self) -> Option<KMap> {
+        MESSAGE_ENTRIES.with(|entries| Some(entries.clone()))
+    }
+}
+
+impl KotoObject for KotoMessage {
+    fn display(&self, ctx: &mut DisplayContext) -> Result<(), RuntimeError> {
+        ctx.append(self.type_literal());
+        let mut fields = fields(&self.message);
+        if let Message::Malformed(error) = &self.message {
+            fields.push(("reason", error.reason().into()));
+        }
+        if !fields.is_empty() {
+            ctx.append(" {");
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    ctx.append(", ");
+                }
+                ctx.append(format!("{name}: "));
+                value.display(ctx)?;
+            }
+            ctx.append("}");
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Option<usize> {
+        Some(self.message.pack().len())
+    }
+
+    fn index(&self, index: &KValue) -> Result<KValue, RuntimeError> {
+        match index {
+            KValue::Number(KNumber::I64(i)) if *i >= 0 => match self.message.pack().get(*i as usize) {
+                Some(&byte) => Ok(byte.into()),
+                None => runtime_error!("index {i} is out of bounds for {}", self.type_literal()),
+            },
+            unexpected => type_error("a positive integer", unexpected),
+        }
+    }
+
+    fn equal(&self, rhs: &KValue) -> Result<bool, RuntimeError> {
+        match rhs {
+            KValue::Object(object) if object.is_a::<KotoMessage>() => {
+                Ok(object.cast::<KotoMessage>()?.message == self.message)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn not_equal(&self, rhs: &KValue) -> Result<bool, RuntimeError> {
+        self.equal(rhs).map(|equal| !equal)
+    }
+
+    fn is_iterable(&self) -> IsIterable {
+        IsIterable::Iterable
+    }
+
+    fn make_iterator(&self, _vm: &mut KotoVm) -> Result<KIterator, RuntimeError> {
+        match byte_list(self.message.pack()) {
+            KValue::List(bytes) => Ok(KIterator::with_list(bytes)),
+            _ => unreachable!(),
+        }
+    }
+}
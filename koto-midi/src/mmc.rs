@@ -0,0 +1,193 @@
+//! Midi machine control, which drives recorders and other transports with universal realtime
+//! system exclusive messages.
+//!
+//! Commands are sent as `F0 7F <device id> 06 <command> F7`, responses of devices use the sub id
+//! 07 instead of 06.
+
+use crate::message::*;
+use crate::mtc::{FrameRate, Timecode};
+use crate::sysex::UNIVERSAL_REALTIME;
+
+const COMMAND: u8 = 0x06;
+const RESPONSE: u8 = 0x07;
+const LOCATE_TARGET: u8 = 0x01;
+const SELECTED_TIME_CODE: u8 = 0x01;
+
+/// A midi machine control command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MmcCommand {
+    Stop,
+    Play,
+    /// Plays once the transport reached the position it is locating to.
+    DeferredPlay,
+    FastForward,
+    Rewind,
+    /// Starts recording, or punches in while playing.
+    RecordStrobe,
+    RecordExit,
+    RecordPause,
+    Pause,
+    Eject,
+    Chase,
+    CommandErrorReset,
+    Reset,
+    /// Moves the transport to a timecode, subframes are hundredths of a frame.
+    Locate {
+        timecode: Timecode,
+        subframes: u8,
+    },
+}
+
+impl MmcCommand {
+    /// The commands which are a single byte, by their codes.
+    pub const SIMPLE: [(u8, MmcCommand); 13] = [
+        (0x01, MmcCommand::Stop),
+        (0x02, MmcCommand::Play),
+        (0x03, MmcCommand::DeferredPlay),
+        (0x04, MmcCommand::FastForward),
+        (0x05, MmcCommand::Rewind),
+        (0x06, MmcCommand::RecordStrobe),
+        (0x07, MmcCommand::RecordExit),
+        (0x08, MmcCommand::RecordPause),
+        (0x09, MmcCommand::Pause),
+        (0x0A, MmcCommand::Eject),
+        (0x0B, MmcCommand::Chase),
+        (0x0C, MmcCommand::CommandErrorReset),
+        (0x0D, MmcCommand::Reset),
+    ];
+
+    pub fn code(&self) -> u8 {
+        match self {
+            MmcCommand::Locate { .. } => 0x44,
+            command => Self::SIMPLE
+                .iter()
+                .find(|(_, simple)| simple == command)
+                .map(|(code, _)| *code)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self {
+            MmcCommand::Locate {
+                timecode,
+                subframes,
+            } => [
+                &[self.code(), 0x06, LOCATE_TARGET][..],
+                &timecode_bytes(timecode, *subframes)[..],
+            ]
+            .concat(),
+            _ => vec![self.code()],
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x44, 0x06, LOCATE_TARGET, timecode @ ..] => {
+                let (timecode, subframes) = timecode_from_bytes(timecode)?;
+                Some(MmcCommand::Locate {
+                    timecode,
+                    subframes,
+                })
+            }
+            &[code] => Self::SIMPLE
+                .iter()
+                .find(|(simple, _)| *simple == code)
+                .map(|(_, command)| *command),
+            _ => None,
+        }
+    }
+}
+
+/// A midi machine control message.
+///
+/// Messages with several commands or with commands and responses which aren't listed here
+/// are not read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mmc {
+    Command {
+        device_id: u8,
+        command: MmcCommand,
+    },
+    /// The response of a device to a request for the timecode of its transport.
+    SelectedTimeCode {
+        device_id: u8,
+        timecode: Timecode,
+        subframes: u8,
+    },
+}
+
+impl Mmc {
+    pub fn device_id(&self) -> u8 {
+        match self {
+            Mmc::Command { device_id, .. } | Mmc::SelectedTimeCode { device_id, .. } => *device_id,
+        }
+    }
+
+    pub fn to_sysex(&self) -> SystemExclusive {
+        let data = match self {
+            Mmc::Command { command, .. } => [&[COMMAND][..], &command.bytes()[..]].concat(),
+            Mmc::SelectedTimeCode {
+                timecode,
+                subframes,
+                ..
+            } => [
+                &[RESPONSE, SELECTED_TIME_CODE][..],
+                &timecode_bytes(timecode, *subframes)[..],
+            ]
+            .concat(),
+        };
+        SystemExclusive::new(
+            &[UNIVERSAL_REALTIME],
+            &[&[self.device_id().min(127)][..], &data[..]].concat(),
+        )
+    }
+
+    /// Reads a midi machine control message, other messages return `None`.
+    pub fn from_sysex(message: &SystemExclusive) -> Option<Self> {
+        if message.manufacturer_id() != [UNIVERSAL_REALTIME] {
+            return None;
+        }
+        match message.message_content() {
+            [device_id, COMMAND, command @ ..] => Some(Mmc::Command {
+                device_id: *device_id,
+                command: MmcCommand::from_bytes(command)?,
+            }),
+            [device_id, RESPONSE, SELECTED_TIME_CODE, timecode @ ..] => {
+                let (timecode, subframes) = timecode_from_bytes(timecode)?;
+                Some(Mmc::SelectedTimeCode {
+                    device_id: *device_id,
+                    timecode,
+                    subframes,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Timecodes are sent as `hh mm ss ff st`, the hours byte carries the frame rate in bits 5 and
+/// 6 and the frames byte carries flags in bits 5 and 6, which are ignored.
+fn timecode_bytes(timecode: &Timecode, subframes: u8) -> [u8; 5] {
+    [
+        (timecode.rate.code() << 5) | timecode.hours,
+        timecode.minutes,
+        timecode.seconds,
+        timecode.frames,
+        subframes.min(99),
+    ]
+}
+
+fn timecode_from_bytes(bytes: &[u8]) -> Option<(Timecode, u8)> {
+    let &[hours, minutes, seconds, frames, subframes] = bytes else {
+        return None;
+    };
+    let timecode = Timecode::new(
+        (hours & 0x1F) as u64,
+        minutes as u64,
+        seconds as u64,
+        (frames & 0x1F) as u64,
+        FrameRate::from_code(hours >> 5),
+    );
+    Some((timecode, subframes))
+}
@@ -0,0 +1,143 @@
+use crate::mmc::*;
+use crate::mtc::Timecode;
+use crate::mtc_module::{koto_timecode, timecode_from_koto};
+use crate::sysex_module::number_arg;
+use crate::KotoMessage;
+use crate::ModuleSettings;
+
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+/// The names of the commands in koto, which are also the names of their constructors.
+fn command_name(command: &MmcCommand) -> &'static str {
+    match command {
+        MmcCommand::Stop => "stop",
+        MmcCommand::Play => "play",
+        MmcCommand::DeferredPlay => "deferred_play",
+        MmcCommand::FastForward => "fast_forward",
+        MmcCommand::Rewind => "rewind",
+        MmcCommand::RecordStrobe => "record_strobe",
+        MmcCommand::RecordExit => "record_exit",
+        MmcCommand::RecordPause => "record_pause",
+        MmcCommand::Pause => "pause",
+        MmcCommand::Eject => "eject",
+        MmcCommand::Chase => "chase",
+        MmcCommand::CommandErrorReset => "command_error_reset",
+        MmcCommand::Reset => "reset",
+        MmcCommand::Locate { .. } => "locate",
+    }
+}
+
+fn insert_timecode(map: &KMap, timecode: Timecode, subframes: u8) {
+    for (key, value) in koto_timecode(timecode).data().iter() {
+        map.insert(key.clone(), value.clone());
+    }
+    map.insert("subframes", subframes);
+}
+
+pub fn koto_mmc(message: &Mmc) -> KMap {
+    let map = KMap::new();
+    match message {
+        Mmc::Command { command, .. } => {
+            map.insert("type", "mmc");
+            map.insert("device_id", message.device_id());
+            map.insert("command", command_name(command));
+            if let MmcCommand::Locate {
+                timecode,
+                subframes,
+            } = command
+            {
+                insert_timecode(&map, *timecode, *subframes);
+            }
+        }
+        Mmc::SelectedTimeCode {
+            timecode,
+            subframes,
+            ..
+        } => {
+            map.insert("type", "mmc_response");
+            map.insert("device_id", message.device_id());
+            map.insert("response", "selected_time_code");
+            insert_timecode(&map, *timecode, *subframes);
+        }
+    }
+    map
+}
+
+fn koto_mmc_message(message: Mmc, strict: bool) -> KValue {
+    KotoMessage::new(message.to_sysex().into())
+        .with_strict(strict)
+        .into()
+}
+
+/// Reads a timecode with an optional `subframes` field, which is 0 by default.
+fn target_from_koto(
+    value: &KValue,
+    strict: bool,
+    context: &str,
+) -> Result<(Timecode, u8), RuntimeError> {
+    let timecode = timecode_from_koto(value, strict, context)?;
+    let subframes = match value {
+        KValue::Map(fields) => match fields.get("subframes") {
+            Some(subframes) => number_arg(&subframes, "subframes", 99, strict, context)?,
+            None => 0,
+        },
+        _ => 0,
+    };
+    Ok((timecode, subframes as u8))
+}
+
+pub fn make_mmc_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+
+    for (_, command) in MmcCommand::SIMPLE {
+        let name = command_name(&command);
+        module.add_fn(name, move |ctx| match ctx.args() {
+            [device_id] => {
+                let device_id = number_arg(device_id, "device_id", 127, settings.strict, name)?;
+                let message = Mmc::Command {
+                    device_id: device_id as u8,
+                    command,
+                };
+                Ok(koto_mmc_message(message, settings.strict))
+            }
+            _ => runtime_error!("{name} requires a device id as its argument"),
+        });
+    }
+
+    module.add_fn("locate", move |ctx| match ctx.args() {
+        [device_id, target] => {
+            let context = "locate";
+            let device_id = number_arg(device_id, "device_id", 127, settings.strict, context)?;
+            let (timecode, subframes) = target_from_koto(target, settings.strict, context)?;
+            let message = Mmc::Command {
+                device_id: device_id as u8,
+                command: MmcCommand::Locate {
+                    timecode,
+                    subframes,
+                },
+            };
+            Ok(koto_mmc_message(message, settings.strict))
+        }
+        _ => runtime_error!("locate requires a device id and a timecode as its arguments"),
+    });
+
+    module.add_fn("selected_time_code", move |ctx| match ctx.args() {
+        [device_id, timecode] => {
+            let context = "selected_time_code";
+            let device_id = number_arg(device_id, "device_id", 127, settings.strict, context)?;
+            let (timecode, subframes) = target_from_koto(timecode, settings.strict, context)?;
+            let message = Mmc::SelectedTimeCode {
+                device_id: device_id as u8,
+                timecode,
+                subframes,
+            };
+            Ok(koto_mmc_message(message, settings.strict))
+        }
+        _ => runtime_error!(
+            "selected_time_code requires a device id and a timecode as its arguments"
+        ),
+    });
+
+    module
+}
@@ -0,0 +1,434 @@
+//! Midi time code, which sends an SMPTE timecode as eight quarter frame messages.
+//!
+//! Each quarter frame message carries a nibble of the timecode, its [QuarterFramePiece] says
+//! which one.
+//!
+//! Four quarter frames are sent per frame, so a complete timecode takes two frames to arrive.
+//! When playing backwards the pieces are sent from 7 to 0.
+//!
+//! Jumps to another position are announced with a full frame message, the universal realtime
+//! system exclusive message `F0 7F 7F 01 01 hh mm ss ff F7`, whose hours byte carries the frame
+//! rate in bits 5 and 6.
+
+use crate::message::*;
+use crate::sysex::{ALL_DEVICES, UNIVERSAL_REALTIME};
+
+/// The frame rates of SMPTE timecodes, in the order of their 2 bit codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FrameRate {
+    Fps24,
+    Fps25,
+    /// 29.97 frames per second, which drops the frame numbers 0 and 1 at the start of each
+    /// minute except every tenth minute.
+    Fps30Drop,
+    #[default]
+    Fps30,
+}
+
+impl FrameRate {
+    /// Uses the 2 lowest bits of the code.
+    pub fn from_code(code: u8) -> Self {
+        match code & 0b11 {
+            0 => FrameRate::Fps24,
+            1 => FrameRate::Fps25,
+            2 => FrameRate::Fps30Drop,
+            _ => FrameRate::Fps30,
+        }
+    }
+    pub fn code(&self) -> u8 {
+        match self {
+            FrameRate::Fps24 => 0,
+            FrameRate::Fps25 => 1,
+            FrameRate::Fps30Drop => 2,
+            FrameRate::Fps30 => 3,
+        }
+    }
+    /// The frame numbers of a second, 30 for drop frame timecodes.
+    pub fn frames_per_second(&self) -> u8 {
+        match self {
+            FrameRate::Fps24 => 24,
+            FrameRate::Fps25 => 25,
+            FrameRate::Fps30Drop | FrameRate::Fps30 => 30,
+        }
+    }
+    /// The frames which are played in a second, 29.97 for drop frame timecodes.
+    pub fn frames_per_second_exact(&self) -> f64 {
+        match self {
+            FrameRate::Fps30Drop => 30_000.0 / 1001.0,
+            _ => self.frames_per_second() as f64,
+        }
+    }
+    /// The frames of a day, after which timecodes wrap around.
+    pub fn frames_per_day(&self) -> u64 {
+        match self {
+            FrameRate::Fps30Drop => 24 * 6 * FRAMES_PER_TEN_DROP_FRAME_MINUTES,
+            _ => 24 * 60 * 60 * self.frames_per_second() as u64,
+        }
+    }
+}
+
+const FRAMES_PER_TEN_DROP_FRAME_MINUTES: u64 = 17982;
+const FRAMES_PER_DROP_FRAME_MINUTE: u64 = 1798;
+
+/// An SMPTE timecode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub rate: FrameRate,
+}
+
+impl Timecode {
+    pub fn try_new(
+        hours: u64,
+        minutes: u64,
+        seconds: u64,
+        frames: u64,
+        rate: FrameRate,
+    ) -> Result<Self, RangeError> {
+        Ok(Self::new(
+            check_range("hours", hours, 23)?,
+            check_range("minutes", minutes, 59)?,
+            check_range("seconds", seconds, 59)?,
+            check_range("frames", frames, rate.frames_per_second() as u64 - 1)?,
+            rate,
+        ))
+    }
+    /// Values out of range are clamped, frames which a drop frame timecode skips are moved
+    /// to the first frame of the minute.
+    pub fn new(hours: u64, minutes: u64, seconds: u64, frames: u64, rate: FrameRate) -> Self {
+        let mut frames = frames.min(rate.frames_per_second() as u64 - 1) as u8;
+        let minutes = minutes.min(59) as u8;
+        let seconds = seconds.min(59) as u8;
+        if rate == FrameRate::Fps30Drop && seconds == 0 && !minutes.is_multiple_of(10) {
+            frames = frames.max(2);
+        }
+        Self {
+            hours: hours.min(23) as u8,
+            minutes,
+            seconds,
+            frames,
+            rate,
+        }
+    }
+
+    /// The frames since midnight.
+    pub fn to_frames(&self) -> u64 {
+        let fps = self.rate.frames_per_second() as u64;
+        let total_minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let frames = (total_minutes * 60 + self.seconds as u64) * fps + self.frames as u64;
+        match self.rate {
+            FrameRate::Fps30Drop => frames - 2 * (total_minutes - total_minutes / 10),
+            _ => frames,
+        }
+    }
+
+    /// The timecode of a number of frames since midnight, wrapping around after a day.
+    pub fn from_frames(frames: u64, rate: FrameRate) -> Self {
+        let mut frames = frames % rate.frames_per_day();
+        if rate == FrameRate::Fps30Drop {
+            let tens = frames / FRAMES_PER_TEN_DROP_FRAME_MINUTES;
+            let rest = frames % FRAMES_PER_TEN_DROP_FRAME_MINUTES;
+            let dropped_minutes = if rest >= 2 {
+                (rest - 2) / FRAMES_PER_DROP_FRAME_MINUTE
+            } else {
+                0
+            };
+            frames += 18 * tens + 2 * dropped_minutes;
+        }
+        let fps = rate.frames_per_second() as u64;
+        let seconds = frames / fps;
+        Self {
+            hours: (seconds / 3600) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+            frames: (frames % fps) as u8,
+            rate,
+        }
+    }
+
+    /// Moves the timecode by a number of frames, wrapping around at midnight.
+    pub fn offset(&self, frames: i64) -> Self {
+        let frames_per_day = self.rate.frames_per_day() as i64;
+        let frames = (self.to_frames() as i64 + frames).rem_euclid(frames_per_day);
+        Self::from_frames(frames as u64, self.rate)
+    }
+
+    /// The seconds since midnight in wall-clock time.
+    pub fn to_seconds(&self) -> f64 {
+        self.to_frames() as f64 / self.rate.frames_per_second_exact()
+    }
+
+    /// The nibble which a quarter frame piece carries.
+    pub fn nibble(&self, piece: QuarterFramePiece) -> u8 {
+        match piece {
+            QuarterFramePiece::FramesLow => self.frames & 0x0F,
+            QuarterFramePiece::FramesHigh => self.frames >> 4,
+            QuarterFramePiece::SecondsLow => self.seconds & 0x0F,
+            QuarterFramePiece::SecondsHigh => self.seconds >> 4,
+            QuarterFramePiece::MinutesLow => self.minutes & 0x0F,
+            QuarterFramePiece::MinutesHigh => self.minutes >> 4,
+            QuarterFramePiece::HoursLow => self.hours & 0x0F,
+            QuarterFramePiece::HoursHighAndRate => (self.rate.code() << 1) | (self.hours >> 4),
+        }
+    }
+
+    pub fn quarter_frame(&self, piece: QuarterFramePiece) -> TimeCodeQuarterFrame {
+        TimeCodeQuarterFrame::from_piece(piece, self.nibble(piece))
+    }
+
+    /// The quarter frames of the timecode in the order they are sent when playing forwards.
+    pub fn to_quarter_frames(&self) -> [TimeCodeQuarterFrame; 8] {
+        QuarterFramePiece::ALL.map(|piece| self.quarter_frame(piece))
+    }
+
+    pub fn to_full_frame(&self) -> SystemExclusive {
+        SystemExclusive::new(
+            &[UNIVERSAL_REALTIME],
+            &[
+                ALL_DEVICES,
+                FULL_FRAME[0],
+                FULL_FRAME[1],
+                (self.rate.code() << 5) | self.hours,
+                self.minutes,
+                self.seconds,
+                self.frames,
+            ],
+        )
+    }
+
+    /// Reads a full frame message, other system exclusive messages return `None`.
+    ///
+    /// Full frame messages for any device are accepted.
+    pub fn from_full_frame(message: &SystemExclusive) -> Option<Self> {
+        if message.manufacturer_id() != [UNIVERSAL_REALTIME] {
+            return None;
+        }
+        match *message.message_content() {
+            [_, sub_id_1, sub_id_2, hours, minutes, seconds, frames]
+                if [sub_id_1, sub_id_2] == FULL_FRAME =>
+            {
+                Some(Self::new(
+                    (hours & 0x1F) as u64,
+                    minutes as u64,
+                    seconds as u64,
+                    frames as u64,
+                    FrameRate::from_code(hours >> 5),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+const FULL_FRAME: [u8; 2] = [0x01, 0x01];
+
+/// The direction in which quarter frames arrive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// Assembles quarter frame messages into timecodes.
+///
+/// The decoder locks once it received all eight pieces in order, in either direction. While it is
+/// locked the timecode advances by a frame for every four quarter frames, and each completed
+/// sequence of pieces corrects it, the correction is kept as the drift. Pieces which arrive out
+/// of order or a change of direction lose the lock until a sequence is completed again.
+///
+/// A completed sequence describes the frame at which its first piece was sent, so the two frames
+/// which passed since then are added to it, or subtracted from it when playing backwards.
+#[derive(Debug, Default, Clone)]
+pub struct MtcDecoder {
+    pieces: [u8; 8],
+    last_piece: Option<QuarterFramePiece>,
+    direction: Option<Direction>,
+    /// The pieces which were received in order in the current direction.
+    in_order: u8,
+    synced: Option<Timecode>,
+    /// The quarter frames received since the timecode was synced, negative when playing backwards.
+    quarter_frames: i64,
+    locked: bool,
+    drift: i64,
+}
+
+impl MtcDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a message to the decoder and returns the current timecode.
+    ///
+    /// A full frame message moves the timecode right away, the decoder locks again with the
+    /// quarter frames which follow it. Other messages are ignored and return `None`, as do
+    /// quarter frames which arrive before a timecode was ever completed.
+    pub fn feed(&mut self, message: &Message) -> Option<Timecode> {
+        match message {
+            Message::TimeCodeQuarterFrame(quarter_frame) => {
+                self.feed_piece(quarter_frame.piece(), quarter_frame.nibble());
+            }
+            Message::SystemExclusive(message) => {
+                let timecode = Timecode::from_full_frame(message)?;
+                *self = Self {
+                    synced: Some(timecode),
+                    ..Self::default()
+                };
+            }
+            _ => return None,
+        }
+        self.timecode()
+    }
+
+    /// Feeds the piece and the nibble of a quarter frame.
+    pub fn feed_piece(&mut self, piece: QuarterFramePiece, nibble: u8) {
+        let direction = match self.last_piece {
+            Some(last) if piece == last.next() => Some(Direction::Forward),
+            Some(last) if piece == last.previous() => Some(Direction::Backward),
+            _ => None,
+        };
+        self.last_piece = Some(piece);
+        self.pieces[piece.number() as usize] = nibble & 0x0F;
+
+        let Some(direction) = direction else {
+            self.direction = None;
+            self.in_order = 1;
+            self.locked = false;
+            return;
+        };
+        if self.direction == Some(direction) {
+            self.in_order = self.in_order.saturating_add(1);
+        } else {
+            self.direction = Some(direction);
+            self.in_order = 2;
+            self.locked = false;
+        }
+        self.quarter_frames += match direction {
+            Direction::Forward => 1,
+            Direction::Backward => -1,
+        };
+
+        let completed = match direction {
+            Direction::Forward => piece == QuarterFramePiece::HoursHighAndRate,
+            Direction::Backward => piece == QuarterFramePiece::FramesLow,
+        };
+        if completed && self.in_order >= 8 {
+            let timecode = self.assemble().offset(match direction {
+                Direction::Forward => 2,
+                Direction::Backward => -2,
+            });
+            let running = self.timecode();
+            self.drift = match running {
+                Some(running) if self.locked && running.rate == timecode.rate => {
+                    signed_frames(running, timecode)
+                }
+                _ => 0,
+            };
+            self.synced = Some(timecode);
+            self.quarter_frames = 0;
+            self.locked = true;
+        }
+    }
+
+    /// The last completed timecode, advanced by the quarter frames received since.
+    pub fn timecode(&self) -> Option<Timecode> {
+        self.synced
+            .map(|timecode| timecode.offset(self.quarter_frames / 4))
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        self.direction
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// The frames by which the last completed timecode differed from the running one.
+    ///
+    /// It is positive when the completed timecode was ahead.
+    pub fn drift(&self) -> i64 {
+        self.drift
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn assemble(&self) -> Timecode {
+        let [frames_low, frames_high, seconds_low, seconds_high, minutes_low, minutes_high, hours_low, hours_high] =
+            self.pieces;
+        let rate = FrameRate::from_code(hours_high >> 1);
+        Timecode::new(
+            (((hours_high & 0b1) << 4) | hours_low) as u64,
+            (((minutes_high & 0b11) << 4) | minutes_low) as u64,
+            (((seconds_high & 0b11) << 4) | seconds_low) as u64,
+            (((frames_high & 0b1) << 4) | frames_low) as u64,
+            rate,
+        )
+    }
+}
+
+/// The frames from one timecode to another, taking the shorter way around midnight.
+fn signed_frames(from: Timecode, to: Timecode) -> i64 {
+    let frames_per_day = from.rate.frames_per_day() as i64;
+    let difference = (to.to_frames() as i64 - from.to_frames() as i64).rem_euclid(frames_per_day);
+    if difference > frames_per_day / 2 {
+        difference - frames_per_day
+    } else {
+        difference
+    }
+}
+
+/// Sends a running timecode as quarter frames.
+///
+/// The pieces of a timecode are sent over two frames, so the timecode advances by two frames
+/// after every eight quarter frames. A quarter frame is due every
+/// [quarter_frame_interval](MtcGenerator::quarter_frame_interval) seconds.
+#[derive(Debug, Default, Clone)]
+pub struct MtcGenerator {
+    timecode: Timecode,
+    piece: QuarterFramePiece,
+}
+
+impl MtcGenerator {
+    pub fn new(timecode: Timecode) -> Self {
+        Self {
+            timecode,
+            piece: QuarterFramePiece::FramesLow,
+        }
+    }
+
+    /// The timecode whose pieces are being sent.
+    pub fn timecode(&self) -> Timecode {
+        self.timecode
+    }
+
+    /// Moves to another timecode and returns the full frame message which announces it.
+    ///
+    /// The next quarter frame starts the pieces of the new timecode.
+    pub fn locate(&mut self, timecode: Timecode) -> SystemExclusive {
+        *self = Self::new(timecode);
+        timecode.to_full_frame()
+    }
+
+    /// The seconds between quarter frames.
+    pub fn quarter_frame_interval(&self) -> f64 {
+        1.0 / (self.timecode.rate.frames_per_second_exact() * 4.0)
+    }
+}
+
+impl Iterator for MtcGenerator {
+    type Item = TimeCodeQuarterFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let message = self.timecode.quarter_frame(self.piece);
+        self.piece = self.piece.next();
+        if self.piece == QuarterFramePiece::FramesLow {
+            self.timecode = self.timecode.offset(2);
+        }
+        Some(message)
+    }
+}
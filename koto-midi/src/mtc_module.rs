@@ -0,0 +1,232 @@
+use crate::controller_object::koto_messages;
+use crate::mtc::*;
+use crate::KotoMessage;
+use crate::ModuleSettings;
+
+use koto::derive::*;
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+/// Frame rates are given by their frames per second in koto, 29.97 for drop frame timecodes.
+fn koto_frame_rate(rate: FrameRate) -> KValue {
+    match rate {
+        FrameRate::Fps30Drop => 29.97.into(),
+        rate => rate.frames_per_second().into(),
+    }
+}
+
+fn frame_rate_from_koto(rate: &KNumber, context: &str) -> Result<FrameRate, RuntimeError> {
+    let rate = f64::from(rate);
+    if rate == 24.0 {
+        Ok(FrameRate::Fps24)
+    } else if rate == 25.0 {
+        Ok(FrameRate::Fps25)
+    } else if (rate - 29.97).abs() < 0.01 {
+        Ok(FrameRate::Fps30Drop)
+    } else if rate == 30.0 {
+        Ok(FrameRate::Fps30)
+    } else {
+        runtime_error!("{context}: rate must be one of 24, 25, 29.97 or 30")
+    }
+}
+
+pub fn koto_timecode(timecode: Timecode) -> KMap {
+    let map = KMap::new();
+    map.insert("hours", timecode.hours);
+    map.insert("minutes", timecode.minutes);
+    map.insert("seconds", timecode.seconds);
+    map.insert("frames", timecode.frames);
+    map.insert("rate", koto_frame_rate(timecode.rate));
+    map
+}
+
+/// Builds a timecode from a map like the ones [koto_timecode] returns.
+///
+/// Missing fields are 0 and the rate is 30 frames per second by default.
+pub(crate) fn timecode_from_koto(
+    value: &KValue,
+    strict: bool,
+    context: &str,
+) -> Result<Timecode, RuntimeError> {
+    let error = format!(
+        "{context}: a timecode must be a map of hours, minutes, seconds, frames and a rate"
+    );
+    let KValue::Map(fields) = value else {
+        return runtime_error!(error);
+    };
+    let rate = match fields.get("rate") {
+        Some(KValue::Number(rate)) => frame_rate_from_koto(&rate, context)?,
+        Some(_) => return runtime_error!(error),
+        None => FrameRate::Fps30,
+    };
+    let field = |name: &str| match fields.get(name) {
+        Some(KValue::Number(KNumber::I64(value))) if value >= 0 => Ok(value as u64),
+        Some(_) => runtime_error!(error.as_str()),
+        None => Ok(0),
+    };
+    let [hours, minutes, seconds, frames] = [
+        field("hours")?,
+        field("minutes")?,
+        field("seconds")?,
+        field("frames")?,
+    ];
+    if strict {
+        match Timecode::try_new(hours, minutes, seconds, frames, rate) {
+            Ok(timecode) => Ok(timecode),
+            Err(error) => runtime_error!("{context}: {error}"),
+        }
+    } else {
+        Ok(Timecode::new(hours, minutes, seconds, frames, rate))
+    }
+}
+
+/// A midi time code decoder which can be used from koto scripts.
+#[derive(Clone, Default, KotoType, KotoCopy)]
+#[koto(type_name = "MtcDecoder")]
+pub struct KotoMtcDecoder {
+    decoder: MtcDecoder,
+}
+
+#[koto_impl]
+impl KotoMtcDecoder {
+    #[koto_method]
+    fn feed(&mut self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        match args {
+            [KValue::Object(message)] if message.is_a::<KotoMessage>() => {
+                let message = message.cast::<KotoMessage>()?;
+                match self.decoder.feed(message.message()) {
+                    Some(timecode) => Ok(koto_timecode(timecode).into()),
+                    None => Ok(KValue::Null),
+                }
+            }
+            _ => runtime_error!("feed requires a single message as its argument"),
+        }
+    }
+
+    #[koto_method]
+    fn timecode(&self) -> KValue {
+        match self.decoder.timecode() {
+            Some(timecode) => koto_timecode(timecode).into(),
+            None => KValue::Null,
+        }
+    }
+
+    #[koto_method]
+    fn direction(&self) -> KValue {
+        match self.decoder.direction() {
+            Some(Direction::Forward) => "forward".into(),
+            Some(Direction::Backward) => "backward".into(),
+            None => KValue::Null,
+        }
+    }
+
+    #[koto_method]
+    fn is_locked(&self) -> KValue {
+        self.decoder.is_locked().into()
+    }
+
+    #[koto_method]
+    fn drift(&self) -> KValue {
+        self.decoder.drift().into()
+    }
+
+    #[koto_method]
+    fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+impl KotoObject for KotoMtcDecoder {}
+
+/// A midi time code generator which can be used from koto scripts.
+#[derive(Clone, KotoType, KotoCopy)]
+#[koto(type_name = "MtcGenerator")]
+pub struct KotoMtcGenerator {
+    generator: MtcGenerator,
+    strict: bool,
+}
+
+#[koto_impl]
+impl KotoMtcGenerator {
+    #[koto_method]
+    fn next(&mut self) -> KValue {
+        match self.generator.next() {
+            Some(message) => KotoMessage::new(message.into())
+                .with_strict(self.strict)
+                .into(),
+            None => KValue::Null,
+        }
+    }
+
+    #[koto_method]
+    fn timecode(&self) -> KValue {
+        koto_timecode(self.generator.timecode()).into()
+    }
+
+    #[koto_method]
+    fn locate(&mut self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        match args {
+            [timecode] => {
+                let timecode = timecode_from_koto(timecode, self.strict, "locate")?;
+                Ok(KotoMessage::new(self.generator.locate(timecode).into())
+                    .with_strict(self.strict)
+                    .into())
+            }
+            _ => runtime_error!("locate requires a timecode as its argument"),
+        }
+    }
+
+    #[koto_method]
+    fn quarter_frame_interval(&self) -> KValue {
+        self.generator.quarter_frame_interval().into()
+    }
+}
+
+impl KotoObject for KotoMtcGenerator {}
+
+pub fn make_mtc_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+
+    module.add_fn("decoder", |ctx| {
+        if ctx.args().is_empty() {
+            Ok(KValue::Object(KotoMtcDecoder::default().into()))
+        } else {
+            runtime_error!("decoder does not take any arguments")
+        }
+    });
+
+    module.add_fn("generator", move |ctx| match ctx.args() {
+        [timecode] => {
+            let timecode = timecode_from_koto(timecode, settings.strict, "midi.mtc.generator")?;
+            Ok(KValue::Object(
+                KotoMtcGenerator {
+                    generator: MtcGenerator::new(timecode),
+                    strict: settings.strict,
+                }
+                .into(),
+            ))
+        }
+        _ => runtime_error!("generator requires a timecode as its argument"),
+    });
+
+    module.add_fn("quarter_frames", move |ctx| match ctx.args() {
+        [timecode] => {
+            let timecode =
+                timecode_from_koto(timecode, settings.strict, "midi.mtc.quarter_frames")?;
+            Ok(koto_messages(timecode.to_quarter_frames(), settings.strict))
+        }
+        _ => runtime_error!("quarter_frames requires a timecode as its argument"),
+    });
+
+    module.add_fn("full_frame", move |ctx| match ctx.args() {
+        [timecode] => {
+            let timecode = timecode_from_koto(timecode, settings.strict, "midi.mtc.full_frame")?;
+            Ok(KotoMessage::new(timecode.to_full_frame().into())
+                .with_strict(settings.strict)
+                .into())
+        }
+        _ => runtime_error!("full_frame requires a timecode as its argument"),
+    });
+
+    module
+}
@@ -0,0 +1,325 @@
+//! The midi tuning standard, which retunes the notes of a device with universal system
+//! exclusive messages.
+//!
+//! Frequencies are sent as a semitone and a 14 bit fraction of a semitone above it, see
+//! [MtsFrequency].
+
+use crate::message::*;
+use crate::sysex::{join_14_bit, UNIVERSAL_NON_REALTIME, UNIVERSAL_REALTIME};
+
+/// A frequency in the format of the tuning standard.
+///
+/// The semitone is the note number of an equal tempered note with A4 = 69 at 440 Hz and the
+/// fraction is in units of 100/16384 cents above it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MtsFrequency {
+    pub semitone: u8,
+    pub fraction: u16,
+}
+
+impl MtsFrequency {
+    /// Leaves the tuning of a note unchanged, it is sent as `7F 7F 7F`.
+    pub const NO_CHANGE: Self = Self {
+        semitone: 127,
+        fraction: 16383,
+    };
+    /// The lowest frequency which can be sent, about 8.18 Hz.
+    pub const MIN: Self = Self {
+        semitone: 0,
+        fraction: 0,
+    };
+    /// The highest frequency which can be sent, about 13289.7 Hz.
+    pub const MAX: Self = Self {
+        semitone: 127,
+        fraction: 16382,
+    };
+
+    /// The closest frequency to the given one in Hz, clamped to the range which can be sent.
+    pub fn from_hz(hz: f64) -> Self {
+        if hz.is_nan() || hz <= Self::MIN.to_hz() {
+            return Self::MIN;
+        }
+        let semitones = 69.0 + 12.0 * (hz / 440.0).log2();
+        let mut semitone = semitones.floor();
+        let mut fraction = ((semitones - semitone) * 16384.0).round();
+        if fraction >= 16384.0 {
+            semitone += 1.0;
+            fraction = 0.0;
+        }
+        if semitone > 127.0 {
+            return Self::MAX;
+        }
+        if semitone == 127.0 {
+            fraction = fraction.min(Self::MAX.fraction as f64);
+        }
+        Self {
+            semitone: semitone as u8,
+            fraction: fraction as u16,
+        }
+    }
+
+    pub fn to_hz(&self) -> f64 {
+        let semitones = self.semitone as f64 + self.fraction as f64 / 16384.0;
+        440.0 * 2f64.powf((semitones - 69.0) / 12.0)
+    }
+
+    pub fn is_no_change(&self) -> bool {
+        *self == Self::NO_CHANGE
+    }
+
+    /// The fraction is sent with its most significant 7 bits first.
+    fn to_bytes(self) -> [u8; 3] {
+        let fraction = self.fraction.min(16383);
+        [
+            self.semitone.min(127),
+            (fraction >> 7) as u8,
+            (fraction & 0x7F) as u8,
+        ]
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            semitone: bytes[0] & 0x7F,
+            fraction: join_14_bit(bytes[2], bytes[1]),
+        }
+    }
+}
+
+/// A message of the midi tuning standard.
+///
+/// Offsets of the scale/octave tuning are in cents, they range from -64 to 63 in the coarse
+/// form and from -100 to almost 100 in the fine form, which sends them with 14 bits.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mts {
+    BulkDumpRequest {
+        device_id: u8,
+        program: u8,
+    },
+    /// The tuning of all 128 notes of a program, the name has up to 16 ascii characters.
+    BulkDump {
+        device_id: u8,
+        program: u8,
+        name: String,
+        frequencies: Vec<MtsFrequency>,
+    },
+    /// Retunes the given notes of a program while they sound.
+    SingleNoteTuningChange {
+        device_id: u8,
+        program: u8,
+        changes: Vec<(u8, MtsFrequency)>,
+    },
+    /// Offsets the 12 notes of each octave on the given channels, bit 0 of the channels is
+    /// channel 1.
+    ScaleOctaveTuning {
+        device_id: u8,
+        realtime: bool,
+        fine: bool,
+        channels: u16,
+        offsets: [f64; 12],
+    },
+}
+
+mod sub_id {
+    pub const MIDI_TUNING: u8 = 0x08;
+    pub const BULK_DUMP_REQUEST: u8 = 0x00;
+    pub const BULK_DUMP: u8 = 0x01;
+    pub const SINGLE_NOTE_TUNING_CHANGE: u8 = 0x02;
+    pub const SCALE_OCTAVE_TUNING: u8 = 0x08;
+    pub const SCALE_OCTAVE_TUNING_FINE: u8 = 0x09;
+}
+
+const NAME_LENGTH: usize = 16;
+
+impl Mts {
+    pub fn device_id(&self) -> u8 {
+        match self {
+            Mts::BulkDumpRequest { device_id, .. }
+            | Mts::BulkDump { device_id, .. }
+            | Mts::SingleNoteTuningChange { device_id, .. }
+            | Mts::ScaleOctaveTuning { device_id, .. } => *device_id,
+        }
+    }
+
+    pub fn is_realtime(&self) -> bool {
+        match self {
+            Mts::SingleNoteTuningChange { .. } => true,
+            Mts::ScaleOctaveTuning { realtime, .. } => *realtime,
+            _ => false,
+        }
+    }
+
+    /// Builds the message, frequencies past the 128 notes of a dump or the 127 changes of a
+    /// single note tuning change are left out and missing ones don't change.
+    pub fn to_sysex(&self) -> SystemExclusive {
+        use sub_id::*;
+
+        let mut data = vec![self.device_id().min(127), MIDI_TUNING];
+        match self {
+            Mts::BulkDumpRequest { program, .. } => {
+                data.extend([BULK_DUMP_REQUEST, (*program).min(127)]);
+            }
+            Mts::BulkDump {
+                program,
+                name,
+                frequencies,
+                ..
+            } => {
+                data.extend([BULK_DUMP, (*program).min(127)]);
+                data.extend(
+                    name.chars()
+                        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+                        .chain(std::iter::repeat(b' '))
+                        .take(NAME_LENGTH),
+                );
+                data.extend(
+                    frequencies
+                        .iter()
+                        .copied()
+                        .chain(std::iter::repeat(MtsFrequency::NO_CHANGE))
+                        .take(128)
+                        .flat_map(MtsFrequency::to_bytes),
+                );
+                data.push(checksum(UNIVERSAL_NON_REALTIME, &data));
+            }
+            Mts::SingleNoteTuningChange {
+                program, changes, ..
+            } => {
+                let changes = &changes[..changes.len().min(127)];
+                data.extend([
+                    SINGLE_NOTE_TUNING_CHANGE,
+                    (*program).min(127),
+                    changes.len() as u8,
+                ]);
+                for (note, frequency) in changes {
+                    data.push((*note).min(127));
+                    data.extend(frequency.to_bytes());
+                }
+            }
+            Mts::ScaleOctaveTuning {
+                fine,
+                channels,
+                offsets,
+                ..
+            } => {
+                data.push(if *fine {
+                    SCALE_OCTAVE_TUNING_FINE
+                } else {
+                    SCALE_OCTAVE_TUNING
+                });
+                data.extend([
+                    ((channels >> 14) & 0b11) as u8,
+                    ((channels >> 7) & 0x7F) as u8,
+                    (channels & 0x7F) as u8,
+                ]);
+                for offset in offsets {
+                    if *fine {
+                        let value = (offset / 100.0 * 8192.0 + 8192.0)
+                            .round()
+                            .clamp(0.0, 16383.0) as u16;
+                        data.extend([(value >> 7) as u8, (value & 0x7F) as u8]);
+                    } else {
+                        data.push((offset.round().clamp(-64.0, 63.0) + 64.0) as u8);
+                    }
+                }
+            }
+        }
+        let manufacturer_id = if self.is_realtime() {
+            UNIVERSAL_REALTIME
+        } else {
+            UNIVERSAL_NON_REALTIME
+        };
+        SystemExclusive::new(&[manufacturer_id], &data)
+    }
+
+    /// Reads a message of the tuning standard, other messages return `None`.
+    ///
+    /// Bulk dumps with a wrong checksum aren't read.
+    pub fn from_sysex(message: &SystemExclusive) -> Option<Self> {
+        use sub_id::*;
+
+        let &[manufacturer_id] = message.manufacturer_id() else {
+            return None;
+        };
+        let content = message.message_content();
+        let [device_id, MIDI_TUNING, sub_id, data @ ..] = content else {
+            return None;
+        };
+        let device_id = *device_id;
+        let message = match (manufacturer_id, *sub_id, data) {
+            (UNIVERSAL_NON_REALTIME, BULK_DUMP_REQUEST, &[program]) => {
+                Mts::BulkDumpRequest { device_id, program }
+            }
+            (UNIVERSAL_NON_REALTIME, BULK_DUMP, [program, data @ ..])
+                if data.len() == NAME_LENGTH + 128 * 3 + 1 =>
+            {
+                let (expected, dump) = content.split_last()?;
+                if checksum(UNIVERSAL_NON_REALTIME, dump) != *expected {
+                    return None;
+                }
+                let (name, frequencies) = data[..data.len() - 1].split_at(NAME_LENGTH);
+                Mts::BulkDump {
+                    device_id,
+                    program: *program,
+                    name: String::from_utf8_lossy(name)
+                        .trim_end_matches([' ', '\0'])
+                        .to_string(),
+                    frequencies: frequencies
+                        .chunks(3)
+                        .map(MtsFrequency::from_bytes)
+                        .collect(),
+                }
+            }
+            (UNIVERSAL_REALTIME, SINGLE_NOTE_TUNING_CHANGE, [program, count, changes @ ..])
+                if changes.len() == *count as usize * 4 =>
+            {
+                Mts::SingleNoteTuningChange {
+                    device_id,
+                    program: *program,
+                    changes: changes
+                        .chunks(4)
+                        .map(|change| (change[0], MtsFrequency::from_bytes(&change[1..])))
+                        .collect(),
+                }
+            }
+            (_, SCALE_OCTAVE_TUNING | SCALE_OCTAVE_TUNING_FINE, [high, middle, low, data @ ..]) => {
+                let fine = *sub_id == SCALE_OCTAVE_TUNING_FINE;
+                if data.len() != if fine { 24 } else { 12 } {
+                    return None;
+                }
+                let mut offsets = [0.0; 12];
+                for (i, offset) in offsets.iter_mut().enumerate() {
+                    *offset = if fine {
+                        (join_14_bit(data[i * 2 + 1], data[i * 2]) as f64 - 8192.0) * 100.0 / 8192.0
+                    } else {
+                        (data[i] & 0x7F) as f64 - 64.0
+                    };
+                }
+                Mts::ScaleOctaveTuning {
+                    device_id,
+                    realtime: manufacturer_id == UNIVERSAL_REALTIME,
+                    fine,
+                    channels: ((*high as u16 & 0b11) << 14)
+                        | ((*middle as u16 & 0x7F) << 7)
+                        | (*low as u16 & 0x7F),
+                    offsets,
+                }
+            }
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+impl From<&Mts> for SystemExclusive {
+    fn from(message: &Mts) -> Self {
+        message.to_sysex()
+    }
+}
+
+/// The checksum of a bulk dump is the exclusive or of the bytes between the status byte and
+/// the checksum, the manufacturer id included.
+fn checksum(manufacturer_id: u8, data: &[u8]) -> u8 {
+    data.iter()
+        .fold(manufacturer_id, |checksum, byte| checksum ^ byte)
+        & 0x7F
+}
@@ -0,0 +1,244 @@
+use crate::message_object::byte_list;
+use crate::mts::*;
+use crate::sysex_module::number_arg;
+use crate::KotoMessage;
+use crate::ModuleSettings;
+
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+/// Frequencies are given in Hz in koto, null leaves the tuning of a note unchanged.
+fn koto_frequency(frequency: MtsFrequency) -> KValue {
+    if frequency.is_no_change() {
+        KValue::Null
+    } else {
+        frequency.to_hz().into()
+    }
+}
+
+fn frequency_from_koto(
+    value: &KValue,
+    strict: bool,
+    context: &str,
+) -> Result<MtsFrequency, RuntimeError> {
+    match value {
+        KValue::Null => Ok(MtsFrequency::NO_CHANGE),
+        KValue::Number(hz) => {
+            let hz = f64::from(hz);
+            let (min, max) = (MtsFrequency::MIN.to_hz(), MtsFrequency::MAX.to_hz());
+            if strict && !(min - 0.001..=max + 0.001).contains(&hz) {
+                return runtime_error!(
+                    "{context}: frequency {hz} is not in range {min:.2}..={max:.2} Hz"
+                );
+            }
+            Ok(MtsFrequency::from_hz(hz))
+        }
+        _ => runtime_error!("{context}: frequencies must be numbers in Hz or null"),
+    }
+}
+
+fn koto_channels(channels: u16) -> KValue {
+    let channels = (0..16u8)
+        .filter(|channel| channels & (1 << channel) != 0)
+        .collect::<Vec<u8>>();
+    byte_list(&channels)
+}
+
+pub fn koto_mts(message: &Mts) -> KMap {
+    let map = KMap::new();
+    let type_literal = match message {
+        Mts::BulkDumpRequest { .. } => "bulk_tuning_dump_request",
+        Mts::BulkDump { .. } => "bulk_tuning_dump",
+        Mts::SingleNoteTuningChange { .. } => "single_note_tuning_change",
+        Mts::ScaleOctaveTuning { .. } => "scale_octave_tuning",
+    };
+    map.insert("type", type_literal);
+    map.insert("device_id", message.device_id());
+    match message {
+        Mts::BulkDumpRequest { program, .. } => map.insert("program", *program),
+        Mts::BulkDump {
+            program,
+            name,
+            frequencies,
+            ..
+        } => {
+            map.insert("program", *program);
+            map.insert("name", name.as_str());
+            let frequencies: Vec<KValue> =
+                frequencies.iter().copied().map(koto_frequency).collect();
+            map.insert(
+                "frequencies",
+                KValue::List(KList::from_slice(&frequencies[..])),
+            );
+        }
+        Mts::SingleNoteTuningChange {
+            program, changes, ..
+        } => {
+            map.insert("program", *program);
+            let changes: Vec<KValue> = changes
+                .iter()
+                .map(|(note, frequency)| {
+                    KValue::List(KList::from_slice(&[
+                        (*note).into(),
+                        koto_frequency(*frequency),
+                    ]))
+                })
+                .collect();
+            map.insert("changes", KValue::List(KList::from_slice(&changes[..])));
+        }
+        Mts::ScaleOctaveTuning {
+            realtime,
+            fine,
+            channels,
+            offsets,
+            ..
+        } => {
+            map.insert("realtime", *realtime);
+            map.insert("fine", *fine);
+            map.insert("channels", koto_channels(*channels));
+            let offsets: Vec<KValue> = offsets.iter().map(|offset| (*offset).into()).collect();
+            map.insert("offsets", KValue::List(KList::from_slice(&offsets[..])));
+        }
+    }
+    map
+}
+
+fn koto_mts_message(message: Mts, strict: bool) -> KValue {
+    KotoMessage::new(message.to_sysex().into())
+        .with_strict(strict)
+        .into()
+}
+
+fn scale_octave_tuning(
+    args: &[KValue],
+    fine: bool,
+    strict: bool,
+    context: &'static str,
+) -> Result<KValue, RuntimeError> {
+    let error = format!(
+        "{context} requires a device id, a list of channels and a list of 12 offsets in cents as \
+         its arguments"
+    );
+    let [device_id, KValue::List(channel_list), KValue::List(offset_list)] = args else {
+        return runtime_error!(error);
+    };
+    let device_id = number_arg(device_id, "device_id", 127, strict, context)?;
+    let mut channels = 0u16;
+    for channel in channel_list.data().iter() {
+        channels |= 1 << number_arg(channel, "channel", 15, strict, context)?;
+    }
+    if offset_list.len() != 12 {
+        return runtime_error!(error);
+    }
+    let (min, max) = if fine { (-100.0, 99.99) } else { (-64.0, 63.0) };
+    let mut offsets = [0.0; 12];
+    for (offset, value) in offsets.iter_mut().zip(offset_list.data().iter()) {
+        let KValue::Number(cents) = value else {
+            return runtime_error!(error);
+        };
+        *offset = f64::from(cents);
+        if strict && !(min..=max).contains(offset) {
+            return runtime_error!(
+                "{context}: offset {offset} is not in range {min}..={max} cents"
+            );
+        }
+    }
+    let message = Mts::ScaleOctaveTuning {
+        device_id: device_id as u8,
+        realtime: false,
+        fine,
+        channels,
+        offsets,
+    };
+    Ok(koto_mts_message(message, strict))
+}
+
+pub fn make_mts_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+
+    module.add_fn("bulk_dump_request", move |ctx| match ctx.args() {
+        [device_id, program] => {
+            let context = "bulk_dump_request";
+            let message = Mts::BulkDumpRequest {
+                device_id: number_arg(device_id, "device_id", 127, settings.strict, context)? as u8,
+                program: number_arg(program, "program", 127, settings.strict, context)? as u8,
+            };
+            Ok(koto_mts_message(message, settings.strict))
+        }
+        _ => {
+            runtime_error!("bulk_dump_request requires a device id and a program as its arguments")
+        }
+    });
+
+    module.add_fn("bulk_dump", move |ctx| {
+        let context = "bulk_dump";
+        let error = "bulk_dump requires a device id, a program, a name and a list of 128 \
+                     frequencies as its arguments";
+        let [device_id, program, KValue::Str(name), KValue::List(frequencies)] = ctx.args() else {
+            return runtime_error!(error);
+        };
+        if frequencies.len() != 128 {
+            return runtime_error!(error);
+        }
+        if settings.strict && (name.len() > 16 || !name.is_ascii()) {
+            return runtime_error!("{context}: the name must have up to 16 ascii characters");
+        }
+        let message = Mts::BulkDump {
+            device_id: number_arg(device_id, "device_id", 127, settings.strict, context)? as u8,
+            program: number_arg(program, "program", 127, settings.strict, context)? as u8,
+            name: name.to_string(),
+            frequencies: frequencies
+                .data()
+                .iter()
+                .map(|frequency| frequency_from_koto(frequency, settings.strict, context))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(koto_mts_message(message, settings.strict))
+    });
+
+    module.add_fn("single_note_tuning_change", move |ctx| {
+        let context = "single_note_tuning_change";
+        let error = "single_note_tuning_change requires a device id, a program and a list of \
+                     [note, frequency] pairs as its arguments";
+        let [device_id, program, KValue::List(change_list)] = ctx.args() else {
+            return runtime_error!(error);
+        };
+        if settings.strict && change_list.len() > 127 {
+            return runtime_error!("{context}: a message can have up to 127 changes");
+        }
+        let mut changes = Vec::with_capacity(change_list.len());
+        for change in change_list.data().iter() {
+            let KValue::List(change) = change else {
+                return runtime_error!(error);
+            };
+            let [note, frequency] = &change.data()[..] else {
+                return runtime_error!(error);
+            };
+            changes.push((
+                number_arg(note, "note", 127, settings.strict, context)? as u8,
+                frequency_from_koto(frequency, settings.strict, context)?,
+            ));
+        }
+        let message = Mts::SingleNoteTuningChange {
+            device_id: number_arg(device_id, "device_id", 127, settings.strict, context)? as u8,
+            program: number_arg(program, "program", 127, settings.strict, context)? as u8,
+            changes,
+        };
+        Ok(koto_mts_message(message, settings.strict))
+    });
+
+    module.add_fn("scale_octave_tuning", move |ctx| {
+        scale_octave_tuning(ctx.args(), false, settings.strict, "scale_octave_tuning")
+    });
+
+    module.add_fn("scale_octave_tuning_fine", move |ctx| {
+        scale_octave_tuning(
+            ctx.args(),
+            true,
+            settings.strict,
+            "scale_octave_tuning_fine",
+        )
+    });
+
+    module
+}
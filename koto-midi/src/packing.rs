@@ -0,0 +1,127 @@
+//! Encodings which carry 8 bit data in the 7 bit data bytes of system exclusive messages.
+//!
+//! Packed data is sent in groups of up to 7 bytes, each group is preceded by a byte which holds
+//! the high bits of the bytes in the group. Nibblized data sends each byte as two bytes which hold
+//! 4 bits each. Sample and firmware dumps use one or the other.
+
+/// The order of the high bits in the first byte of a packed group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BitOrder {
+    /// The high bit of the first byte of the group is bit 6.
+    #[default]
+    MsbFirst,
+    /// The high bit of the first byte of the group is bit 0.
+    LsbFirst,
+}
+
+impl BitOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BitOrder::MsbFirst => "msb_first",
+            BitOrder::LsbFirst => "lsb_first",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "msb_first" => Some(BitOrder::MsbFirst),
+            "lsb_first" => Some(BitOrder::LsbFirst),
+            _ => None,
+        }
+    }
+
+    fn bit(&self, index: usize) -> usize {
+        match self {
+            BitOrder::MsbFirst => 6 - index,
+            BitOrder::LsbFirst => index,
+        }
+    }
+}
+
+/// The order of the nibbles of a nibblized byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NibbleOrder {
+    #[default]
+    HighFirst,
+    LowFirst,
+}
+
+impl NibbleOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NibbleOrder::HighFirst => "high_first",
+            NibbleOrder::LowFirst => "low_first",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "high_first" => Some(NibbleOrder::HighFirst),
+            "low_first" => Some(NibbleOrder::LowFirst),
+            _ => None,
+        }
+    }
+}
+
+/// The number of bytes which packing the given number of bytes takes.
+pub fn packed_length(length: usize) -> usize {
+    length + length.div_ceil(7)
+}
+
+/// Packs 8 bit data into 7 bit data bytes.
+pub fn pack7(data: &[u8], order: BitOrder) -> Vec<u8> {
+    let mut packed = Vec::with_capacity(packed_length(data.len()));
+    for group in data.chunks(7) {
+        let high_bits = group.iter().enumerate().fold(0, |bits, (index, byte)| {
+            bits | (byte >> 7) << order.bit(index)
+        });
+        packed.push(high_bits);
+        packed.extend(group.iter().map(|byte| byte & 0x7F));
+    }
+    packed
+}
+
+/// Unpacks data which was packed by [pack7].
+///
+/// A trailing group without bytes is ignored, `None` is returned when a byte isn't a data byte.
+pub fn unpack7(packed: &[u8], order: BitOrder) -> Option<Vec<u8>> {
+    if packed.iter().any(|&byte| byte > 0x7F) {
+        return None;
+    }
+    let mut data = Vec::with_capacity(packed.len());
+    for group in packed.chunks(8) {
+        let (high_bits, bytes) = group.split_first()?;
+        data.extend(
+            bytes
+                .iter()
+                .enumerate()
+                .map(|(index, byte)| byte | ((high_bits >> order.bit(index)) & 1) << 7),
+        );
+    }
+    Some(data)
+}
+
+/// Splits each byte into two bytes which hold 4 bits each.
+pub fn nibblize(data: &[u8], order: NibbleOrder) -> Vec<u8> {
+    data.iter()
+        .flat_map(|byte| match order {
+            NibbleOrder::HighFirst => [byte >> 4, byte & 0x0F],
+            NibbleOrder::LowFirst => [byte & 0x0F, byte >> 4],
+        })
+        .collect()
+}
+
+/// Joins nibblized data, `None` is returned for an odd number of bytes or a byte above 0x0F.
+pub fn denibblize(nibbles: &[u8], order: NibbleOrder) -> Option<Vec<u8>> {
+    if !nibbles.len().is_multiple_of(2) || nibbles.iter().any(|&nibble| nibble > 0x0F) {
+        return None;
+    }
+    let data = nibbles
+        .chunks(2)
+        .map(|pair| match order {
+            NibbleOrder::HighFirst => pair[0] << 4 | pair[1],
+            NibbleOrder::LowFirst => pair[1] << 4 | pair[0],
+        })
+        .collect();
+    Some(data)
+}
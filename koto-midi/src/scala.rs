@@ -0,0 +1,268 @@
+//! Scala scale (`.scl`) and keyboard mapping (`.kbm`) files, which describe microtonal tunings.
+//!
+//! Lines starting with `!` are comments. A scale file has a description line, the number of
+//! pitches and then the pitches of the scale above its 1/1, either in cents when they contain a
+//! period or as ratios. The last pitch is the period of the scale, usually 2/1.
+
+use std::path::Path;
+
+/// A pitch of a scale above its 1/1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pitch {
+    Cents(f64),
+    Ratio { numerator: u64, denominator: u64 },
+}
+
+impl Pitch {
+    pub fn cents(&self) -> f64 {
+        match self {
+            Pitch::Cents(cents) => *cents,
+            Pitch::Ratio {
+                numerator,
+                denominator,
+            } => 1200.0 * (*numerator as f64 / *denominator as f64).log2(),
+        }
+    }
+}
+
+/// A scale read from a Scala file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    pub description: String,
+    /// The pitches above the 1/1, the last one is the period of the scale.
+    pub pitches: Vec<Pitch>,
+}
+
+/// Maps the keys of a keyboard to the degrees of a scale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    /// Keys outside of `first_note..=last_note` are not mapped.
+    pub first_note: u8,
+    pub last_note: u8,
+    /// The key which plays the 1/1 of the scale.
+    pub middle_note: u8,
+    /// The key which is tuned to the reference frequency in Hz.
+    pub reference_note: u8,
+    pub reference_frequency: f64,
+    /// The degree which the mapping repeats at, 0 for the period of the scale.
+    pub octave_degree: usize,
+    /// The degrees of the keys from the middle note on, repeating above and below it. `None` is
+    /// a key which isn't mapped and an empty mapping maps each key to the next degree.
+    pub mapping: Vec<Option<usize>>,
+}
+
+impl Default for KeyboardMapping {
+    /// Maps the scale linearly with its 1/1 on middle C at 261.63 Hz.
+    fn default() -> Self {
+        Self {
+            first_note: 0,
+            last_note: 127,
+            middle_note: 60,
+            reference_note: 60,
+            reference_frequency: 440.0 * 2f64.powf(-9.0 / 12.0),
+            octave_degree: 0,
+            mapping: Vec::new(),
+        }
+    }
+}
+
+/// The reason a Scala file could not be read.
+///
+/// Lines are counted from 1.
+#[derive(Debug)]
+pub enum ScalaError {
+    Io(std::io::Error),
+    /// The file ends before all of its values were read.
+    UnexpectedEnd,
+    /// The line doesn't start with a valid value.
+    InvalidValue {
+        line: usize,
+    },
+    /// The scale has no pitches, so it has no period.
+    EmptyScale,
+    /// The reference note of the keyboard mapping is mapped to no degree.
+    UnmappedReference,
+}
+
+impl std::fmt::Display for ScalaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScalaError::Io(error) => write!(f, "{error}"),
+            ScalaError::UnexpectedEnd => write!(f, "the file ends unexpectedly"),
+            ScalaError::InvalidValue { line } => write!(f, "invalid value on line {line}"),
+            ScalaError::EmptyScale => write!(f, "the scale has no pitches"),
+            ScalaError::UnmappedReference => write!(f, "the reference note is not mapped"),
+        }
+    }
+}
+
+impl std::error::Error for ScalaError {}
+
+impl From<std::io::Error> for ScalaError {
+    fn from(error: std::io::Error) -> Self {
+        ScalaError::Io(error)
+    }
+}
+
+/// Reads the lines of a file which aren't comments, with their line numbers.
+struct Lines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().enumerate(),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<(usize, &'a str), ScalaError> {
+        self.lines
+            .find(|(_, line)| !line.starts_with('!'))
+            .map(|(index, line)| (index + 1, line.trim()))
+            .ok_or(ScalaError::UnexpectedEnd)
+    }
+
+    /// Reads the first word of the next line, anything after it is ignored.
+    fn value<T: std::str::FromStr>(
+        &mut self,
+        is_valid: impl Fn(&T) -> bool,
+    ) -> Result<T, ScalaError> {
+        let (line, text) = self.next_line()?;
+        text.split_whitespace()
+            .next()
+            .and_then(|word| word.parse().ok())
+            .filter(is_valid)
+            .ok_or(ScalaError::InvalidValue { line })
+    }
+}
+
+fn parse_pitch(word: &str) -> Option<Pitch> {
+    if word.contains('.') {
+        return word.parse().ok().map(Pitch::Cents);
+    }
+    let (numerator, denominator) = match word.split_once('/') {
+        Some((numerator, denominator)) => (numerator.parse().ok()?, denominator.parse().ok()?),
+        None => (word.parse().ok()?, 1),
+    };
+    if numerator == 0 || denominator == 0 {
+        return None;
+    }
+    Some(Pitch::Ratio {
+        numerator,
+        denominator,
+    })
+}
+
+impl Scale {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ScalaError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ScalaError> {
+        let mut lines = Lines::new(text);
+        let (_, description) = lines.next_line()?;
+        let count: usize = lines.value(|_| true)?;
+        if count == 0 {
+            return Err(ScalaError::EmptyScale);
+        }
+        let pitches = (0..count)
+            .map(|_| {
+                let (line, text) = lines.next_line()?;
+                text.split_whitespace()
+                    .next()
+                    .and_then(parse_pitch)
+                    .ok_or(ScalaError::InvalidValue { line })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            description: description.to_string(),
+            pitches,
+        })
+    }
+
+    /// The cents of a degree of the scale above or below its 1/1, degrees past the length of the
+    /// scale repeat it at its period.
+    pub fn degree_cents(&self, degree: i64) -> f64 {
+        let length = self.pitches.len() as i64;
+        let period = self.pitches.last().map_or(0.0, Pitch::cents);
+        let index = degree.rem_euclid(length) as usize;
+        let cents = if index == 0 {
+            0.0
+        } else {
+            self.pitches[index - 1].cents()
+        };
+        degree.div_euclid(length) as f64 * period + cents
+    }
+
+    /// The frequencies in Hz of the 128 notes, `None` for the ones which aren't mapped.
+    pub fn frequencies(&self, mapping: &KeyboardMapping) -> Result<[Option<f64>; 128], ScalaError> {
+        if self.pitches.is_empty() {
+            return Err(ScalaError::EmptyScale);
+        }
+        let octave = match mapping.octave_degree {
+            0 => self.degree_cents(self.pitches.len() as i64),
+            degree => self.degree_cents(degree as i64),
+        };
+        let cents = |note: u8| {
+            let offset = note as i64 - mapping.middle_note as i64;
+            if mapping.mapping.is_empty() {
+                return Some(self.degree_cents(offset));
+            }
+            let size = mapping.mapping.len() as i64;
+            let degree = mapping.mapping[offset.rem_euclid(size) as usize]?;
+            Some(offset.div_euclid(size) as f64 * octave + self.degree_cents(degree as i64))
+        };
+        let reference = cents(mapping.reference_note).ok_or(ScalaError::UnmappedReference)?;
+
+        let mut frequencies = [None; 128];
+        for note in mapping.first_note..=mapping.last_note.min(127) {
+            frequencies[note as usize] = cents(note)
+                .map(|cents| mapping.reference_frequency * 2f64.powf((cents - reference) / 1200.0));
+        }
+        Ok(frequencies)
+    }
+}
+
+impl KeyboardMapping {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ScalaError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Entries of the mapping are degrees or `x` for keys which aren't mapped, missing entries
+    /// aren't mapped either. A mapping can't repeat over more keys than there are notes.
+    pub fn parse(text: &str) -> Result<Self, ScalaError> {
+        let mut lines = Lines::new(text);
+        let size: usize = lines.value(|size: &usize| *size <= 128)?;
+        let note = |note: &u8| *note <= 127;
+        let first_note = lines.value(note)?;
+        let last_note = lines.value(note)?;
+        let middle_note = lines.value(note)?;
+        let reference_note = lines.value(note)?;
+        let reference_frequency = lines.value(|frequency: &f64| *frequency > 0.0)?;
+        let octave_degree = lines.value(|_| true)?;
+        let mut mapping = vec![];
+        for _ in 0..size {
+            let Ok((line, text)) = lines.next_line() else {
+                mapping.push(None);
+                continue;
+            };
+            match text.split_whitespace().next() {
+                Some("x") | None => mapping.push(None),
+                Some(word) => match word.parse() {
+                    Ok(degree) => mapping.push(Some(degree)),
+                    Err(_) => return Err(ScalaError::InvalidValue { line }),
+                },
+            }
+        }
+        Ok(Self {
+            first_note,
+            last_note,
+            middle_note,
+            reference_note,
+            reference_frequency,
+            octave_degree,
+            mapping,
+        })
+    }
+}
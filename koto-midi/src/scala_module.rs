@@ -0,0 +1,189 @@
+use crate::scala::*;
+
+use koto::derive::*;
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+pub fn koto_keyboard_mapping(mapping: &KeyboardMapping) -> KMap {
+    let map = KMap::new();
+    map.insert("first_note", mapping.first_note);
+    map.insert("last_note", mapping.last_note);
+    map.insert("middle_note", mapping.middle_note);
+    map.insert("reference_note", mapping.reference_note);
+    map.insert("reference_frequency", mapping.reference_frequency);
+    map.insert("octave_degree", mapping.octave_degree as u64);
+    let degrees = mapping
+        .mapping
+        .iter()
+        .map(|degree| match degree {
+            Some(degree) => (*degree as u64).into(),
+            None => KValue::Null,
+        })
+        .collect::<Vec<KValue>>();
+    map.insert("mapping", KValue::List(KList::from_slice(&degrees[..])));
+    map
+}
+
+/// Builds a keyboard mapping from a map like the ones [koto_keyboard_mapping] returns.
+///
+/// Missing fields are taken from the default mapping.
+fn keyboard_mapping_from_koto(
+    fields: &KMap,
+    context: &str,
+) -> Result<KeyboardMapping, RuntimeError> {
+    let mut mapping = KeyboardMapping::default();
+    let integer = |name: &str, max: u64| match fields.get(name) {
+        Some(KValue::Number(KNumber::I64(value))) if value >= 0 && value as u64 <= max => {
+            Ok(Some(value as u64))
+        }
+        Some(_) => runtime_error!("{context}: {name} must be an integer in range 0..={max}"),
+        None => Ok(None),
+    };
+    let notes = [
+        ("first_note", &mut mapping.first_note),
+        ("last_note", &mut mapping.last_note),
+        ("middle_note", &mut mapping.middle_note),
+        ("reference_note", &mut mapping.reference_note),
+    ];
+    for (name, note) in notes {
+        if let Some(value) = integer(name, 127)? {
+            *note = value as u8;
+        }
+    }
+    if let Some(degree) = integer("octave_degree", u32::MAX as u64)? {
+        mapping.octave_degree = degree as usize;
+    }
+    match fields.get("reference_frequency") {
+        Some(KValue::Number(frequency)) if f64::from(&frequency) > 0.0 => {
+            mapping.reference_frequency = f64::from(&frequency);
+        }
+        Some(_) => {
+            return runtime_error!("{context}: reference_frequency must be a positive number")
+        }
+        None => {}
+    }
+    match fields.get("mapping") {
+        Some(KValue::List(degrees)) => {
+            mapping.mapping = degrees
+                .data()
+                .iter()
+                .map(|degree| match degree {
+                    KValue::Number(KNumber::I64(degree)) if *degree >= 0 => {
+                        Ok(Some(*degree as usize))
+                    }
+                    KValue::Null => Ok(None),
+                    _ => runtime_error!("{context}: mapping must be a list of degrees or null"),
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        Some(_) => return runtime_error!("{context}: mapping must be a list of degrees or null"),
+        None => {}
+    }
+    Ok(mapping)
+}
+
+/// A scala scale which can be used from koto scripts.
+#[derive(Clone, KotoType, KotoCopy)]
+#[koto(type_name = "Scale")]
+pub struct KotoScale {
+    scale: Scale,
+}
+
+#[koto_impl]
+impl KotoScale {
+    #[koto_method]
+    fn description(&self) -> KValue {
+        self.scale.description.as_str().into()
+    }
+
+    #[koto_method]
+    fn size(&self) -> KValue {
+        (self.scale.pitches.len() as u64).into()
+    }
+
+    /// The cents of the pitches, the last one is the period.
+    #[koto_method]
+    fn cents(&self) -> KValue {
+        let cents = self
+            .scale
+            .pitches
+            .iter()
+            .map(|pitch| pitch.cents().into())
+            .collect::<Vec<KValue>>();
+        KValue::List(KList::from_slice(&cents[..]))
+    }
+
+    #[koto_method]
+    fn frequencies(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let mapping = match args {
+            [] => KeyboardMapping::default(),
+            [KValue::Map(fields)] => keyboard_mapping_from_koto(fields, "frequencies")?,
+            _ => {
+                return runtime_error!(
+                    "frequencies takes an optional keyboard mapping as its argument"
+                )
+            }
+        };
+        match self.scale.frequencies(&mapping) {
+            Ok(frequencies) => {
+                let frequencies = frequencies
+                    .iter()
+                    .map(|frequency| match frequency {
+                        Some(frequency) => (*frequency).into(),
+                        None => KValue::Null,
+                    })
+                    .collect::<Vec<KValue>>();
+                Ok(KValue::List(KList::from_slice(&frequencies[..])))
+            }
+            Err(error) => runtime_error!("frequencies: {error}"),
+        }
+    }
+}
+
+impl KotoObject for KotoScale {}
+
+fn koto_scale(scale: Scale) -> KValue {
+    KValue::Object(KotoScale { scale }.into())
+}
+
+pub fn make_scala_module() -> KMap {
+    let module = KMap::new();
+
+    module.add_fn("read", |ctx| match ctx.args() {
+        [KValue::Str(path)] => match Scale::read(path.as_str()) {
+            Ok(scale) => Ok(koto_scale(scale)),
+            Err(error) => runtime_error!("midi.scala.read: Unable to read file '{path}': {error}"),
+        },
+        _ => runtime_error!("read requires a path as its argument"),
+    });
+
+    module.add_fn("parse", |ctx| match ctx.args() {
+        [KValue::Str(text)] => match Scale::parse(text.as_str()) {
+            Ok(scale) => Ok(koto_scale(scale)),
+            Err(error) => runtime_error!("midi.scala.parse: {error}"),
+        },
+        _ => runtime_error!("parse requires the text of a scale file as its argument"),
+    });
+
+    module.add_fn("read_mapping", |ctx| match ctx.args() {
+        [KValue::Str(path)] => match KeyboardMapping::read(path.as_str()) {
+            Ok(mapping) => Ok(koto_keyboard_mapping(&mapping).into()),
+            Err(error) => {
+                runtime_error!("midi.scala.read_mapping: Unable to read file '{path}': {error}")
+            }
+        },
+        _ => runtime_error!("read_mapping requires a path as its argument"),
+    });
+
+    module.add_fn("parse_mapping", |ctx| match ctx.args() {
+        [KValue::Str(text)] => match KeyboardMapping::parse(text.as_str()) {
+            Ok(mapping) => Ok(koto_keyboard_mapping(&mapping).into()),
+            Err(error) => runtime_error!("midi.scala.parse_mapping: {error}"),
+        },
+        _ => runtime_error!(
+            "parse_mapping requires the text of a keyboard mapping file as its argument"
+        ),
+    });
+
+    module
+}
@@ -0,0 +1,239 @@
+//! Universal system exclusive messages, which the midi specification defines for all
+//! manufacturers.
+//!
+//! They are sent with the manufacturer id 0x7E (non-realtime) or 0x7F (realtime), followed by
+//! a device id and two sub ids which identify the message. The device id 0x7F addresses all
+//! devices.
+
+use crate::message::*;
+
+pub const UNIVERSAL_NON_REALTIME: u8 = 0x7E;
+pub const UNIVERSAL_REALTIME: u8 = 0x7F;
+/// The device id which addresses all devices.
+pub const ALL_DEVICES: u8 = 0x7F;
+
+/// A universal system exclusive message.
+///
+/// The 14 bit values are sent with their least significant 7 bits first. Values out of range
+/// are clamped when the message is built.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UniversalSysEx {
+    IdentityRequest {
+        device_id: u8,
+    },
+    /// The family and member codes are defined by the manufacturer, as is the software version.
+    IdentityReply {
+        device_id: u8,
+        manufacturer_id: Vec<u8>,
+        family: u16,
+        member: u16,
+        version: [u8; 4],
+    },
+    Gm1SystemOn {
+        device_id: u8,
+    },
+    GmSystemOff {
+        device_id: u8,
+    },
+    Gm2SystemOn {
+        device_id: u8,
+    },
+    MasterVolume {
+        device_id: u8,
+        volume: u16,
+    },
+    /// 8192 is the center.
+    MasterBalance {
+        device_id: u8,
+        balance: u16,
+    },
+    /// 8192 is A440, 0 and 16383 are a semitone below and above it.
+    MasterFineTuning {
+        device_id: u8,
+        fine_tuning: u16,
+    },
+    /// Semitones, 64 is A440.
+    MasterCoarseTuning {
+        device_id: u8,
+        coarse_tuning: u8,
+    },
+}
+
+mod sub_id {
+    pub const GENERAL_INFORMATION: u8 = 0x06;
+    pub const IDENTITY_REQUEST: u8 = 0x01;
+    pub const IDENTITY_REPLY: u8 = 0x02;
+    pub const GENERAL_MIDI: u8 = 0x09;
+    pub const GM1_SYSTEM_ON: u8 = 0x01;
+    pub const GM_SYSTEM_OFF: u8 = 0x02;
+    pub const GM2_SYSTEM_ON: u8 = 0x03;
+    pub const DEVICE_CONTROL: u8 = 0x04;
+    pub const MASTER_VOLUME: u8 = 0x01;
+    pub const MASTER_BALANCE: u8 = 0x02;
+    pub const MASTER_FINE_TUNING: u8 = 0x03;
+    pub const MASTER_COARSE_TUNING: u8 = 0x04;
+}
+
+impl UniversalSysEx {
+    pub fn device_id(&self) -> u8 {
+        match self {
+            UniversalSysEx::IdentityRequest { device_id }
+            | UniversalSysEx::IdentityReply { device_id, .. }
+            | UniversalSysEx::Gm1SystemOn { device_id }
+            | UniversalSysEx::GmSystemOff { device_id }
+            | UniversalSysEx::Gm2SystemOn { device_id }
+            | UniversalSysEx::MasterVolume { device_id, .. }
+            | UniversalSysEx::MasterBalance { device_id, .. }
+            | UniversalSysEx::MasterFineTuning { device_id, .. }
+            | UniversalSysEx::MasterCoarseTuning { device_id, .. } => *device_id,
+        }
+    }
+
+    /// Device control messages are realtime, the others are non-realtime.
+    pub fn is_realtime(&self) -> bool {
+        matches!(
+            self,
+            UniversalSysEx::MasterVolume { .. }
+                | UniversalSysEx::MasterBalance { .. }
+                | UniversalSysEx::MasterFineTuning { .. }
+                | UniversalSysEx::MasterCoarseTuning { .. }
+        )
+    }
+
+    /// Whether a device with the given id should respond to the message.
+    pub fn is_addressed_to(&self, device_id: u8) -> bool {
+        self.device_id() == ALL_DEVICES || self.device_id() == device_id
+    }
+
+    pub fn to_sysex(&self) -> SystemExclusive {
+        use sub_id::*;
+
+        let mut data = match self {
+            UniversalSysEx::IdentityRequest { .. } => vec![GENERAL_INFORMATION, IDENTITY_REQUEST],
+            UniversalSysEx::IdentityReply {
+                manufacturer_id,
+                family,
+                member,
+                version,
+                ..
+            } => {
+                let mut data = vec![GENERAL_INFORMATION, IDENTITY_REPLY];
+                data.extend(manufacturer_id.iter().map(|byte| byte & 0x7F));
+                data.extend(split_14_bit(*family));
+                data.extend(split_14_bit(*member));
+                data.extend(version.iter().map(|byte| byte & 0x7F));
+                data
+            }
+            UniversalSysEx::Gm1SystemOn { .. } => vec![GENERAL_MIDI, GM1_SYSTEM_ON],
+            UniversalSysEx::GmSystemOff { .. } => vec![GENERAL_MIDI, GM_SYSTEM_OFF],
+            UniversalSysEx::Gm2SystemOn { .. } => vec![GENERAL_MIDI, GM2_SYSTEM_ON],
+            UniversalSysEx::MasterVolume { volume, .. } => {
+                [[DEVICE_CONTROL, MASTER_VOLUME], split_14_bit(*volume)].concat()
+            }
+            UniversalSysEx::MasterBalance { balance, .. } => {
+                [[DEVICE_CONTROL, MASTER_BALANCE], split_14_bit(*balance)].concat()
+            }
+            UniversalSysEx::MasterFineTuning { fine_tuning, .. } => [
+                [DEVICE_CONTROL, MASTER_FINE_TUNING],
+                split_14_bit(*fine_tuning),
+            ]
+            .concat(),
+            UniversalSysEx::MasterCoarseTuning { coarse_tuning, .. } => vec![
+                DEVICE_CONTROL,
+                MASTER_COARSE_TUNING,
+                0x00,
+                (*coarse_tuning).min(127),
+            ],
+        };
+        data.insert(0, self.device_id().min(127));
+        let manufacturer_id = if self.is_realtime() {
+            UNIVERSAL_REALTIME
+        } else {
+            UNIVERSAL_NON_REALTIME
+        };
+        SystemExclusive::new(&[manufacturer_id], &data)
+    }
+
+    /// Reads a universal system exclusive message, other messages return `None`.
+    pub fn from_sysex(message: &SystemExclusive) -> Option<Self> {
+        use sub_id::*;
+
+        let &[manufacturer_id] = message.manufacturer_id() else {
+            return None;
+        };
+        let [device_id, sub_id_1, sub_id_2, data @ ..] = message.message_content() else {
+            return None;
+        };
+        let device_id = *device_id;
+        let message = match (manufacturer_id, *sub_id_1, *sub_id_2, data) {
+            (UNIVERSAL_NON_REALTIME, GENERAL_INFORMATION, IDENTITY_REQUEST, []) => {
+                UniversalSysEx::IdentityRequest { device_id }
+            }
+            (UNIVERSAL_NON_REALTIME, GENERAL_INFORMATION, IDENTITY_REPLY, data) => {
+                let manufacturer_id_length = if data.first() == Some(&0x00) { 3 } else { 1 };
+                if data.len() != manufacturer_id_length + 8 {
+                    return None;
+                }
+                let (manufacturer_id, data) = data.split_at(manufacturer_id_length);
+                UniversalSysEx::IdentityReply {
+                    device_id,
+                    manufacturer_id: manufacturer_id.to_vec(),
+                    family: join_14_bit(data[0], data[1]),
+                    member: join_14_bit(data[2], data[3]),
+                    version: [data[4], data[5], data[6], data[7]],
+                }
+            }
+            (UNIVERSAL_NON_REALTIME, GENERAL_MIDI, GM1_SYSTEM_ON, []) => {
+                UniversalSysEx::Gm1SystemOn { device_id }
+            }
+            (UNIVERSAL_NON_REALTIME, GENERAL_MIDI, GM_SYSTEM_OFF, []) => {
+                UniversalSysEx::GmSystemOff { device_id }
+            }
+            (UNIVERSAL_NON_REALTIME, GENERAL_MIDI, GM2_SYSTEM_ON, []) => {
+                UniversalSysEx::Gm2SystemOn { device_id }
+            }
+            (UNIVERSAL_REALTIME, DEVICE_CONTROL, MASTER_VOLUME, &[lsb, msb]) => {
+                UniversalSysEx::MasterVolume {
+                    device_id,
+                    volume: join_14_bit(lsb, msb),
+                }
+            }
+            (UNIVERSAL_REALTIME, DEVICE_CONTROL, MASTER_BALANCE, &[lsb, msb]) => {
+                UniversalSysEx::MasterBalance {
+                    device_id,
+                    balance: join_14_bit(lsb, msb),
+                }
+            }
+            (UNIVERSAL_REALTIME, DEVICE_CONTROL, MASTER_FINE_TUNING, &[lsb, msb]) => {
+                UniversalSysEx::MasterFineTuning {
+                    device_id,
+                    fine_tuning: join_14_bit(lsb, msb),
+                }
+            }
+            (UNIVERSAL_REALTIME, DEVICE_CONTROL, MASTER_COARSE_TUNING, &[_, msb]) => {
+                UniversalSysEx::MasterCoarseTuning {
+                    device_id,
+                    coarse_tuning: msb,
+                }
+            }
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+impl From<&UniversalSysEx> for SystemExclusive {
+    fn from(message: &UniversalSysEx) -> Self {
+        message.to_sysex()
+    }
+}
+
+/// Splits a 14 bit value into its least and most significant 7 bits.
+pub(crate) fn split_14_bit(value: u16) -> [u8; 2] {
+    let value = value.min(16383);
+    [(value & 0x7F) as u8, (value >> 7) as u8]
+}
+
+pub(crate) fn join_14_bit(lsb: u8, msb: u8) -> u16 {
+    ((msb as u16 & 0x7F) << 7) | (lsb as u16 & 0x7F)
+}
@@ -0,0 +1,358 @@
+use crate::dump::{self, SysexAssembler};
+use crate::dump_object::KotoSysexAssembler;
+use crate::manufacturer;
+use crate::message::*;
+use crate::message_object::{byte_list, check_manufacturer_id};
+use crate::mmc::Mmc;
+use crate::mmc_module::koto_mmc;
+use crate::mts::Mts;
+use crate::mts_module::koto_mts;
+use crate::mtc::Timecode;
+use crate::mtc_module::koto_timecode;
+use crate::packing::{self, BitOrder, NibbleOrder};
+use crate::sysex::*;
+use crate::KotoMessage;
+use crate::ModuleSettings;
+
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+pub fn koto_universal_sysex(message: &UniversalSysEx) -> KMap {
+    let map = KMap::new();
+    let type_literal = match message {
+        UniversalSysEx::IdentityRequest { .. } => "identity_request",
+        UniversalSysEx::IdentityReply { .. } => "identity_reply",
+        UniversalSysEx::Gm1SystemOn { .. } => "gm1_system_on",
+        UniversalSysEx::GmSystemOff { .. } => "gm_system_off",
+        UniversalSysEx::Gm2SystemOn { .. } => "gm2_system_on",
+        UniversalSysEx::MasterVolume { .. } => "master_volume",
+        UniversalSysEx::MasterBalance { .. } => "master_balance",
+        UniversalSysEx::MasterFineTuning { .. } => "master_fine_tuning",
+        UniversalSysEx::MasterCoarseTuning { .. } => "master_coarse_tuning",
+    };
+    map.insert("type", type_literal);
+    map.insert("device_id", message.device_id());
+    match message {
+        UniversalSysEx::IdentityReply {
+            manufacturer_id,
+            family,
+            member,
+            version,
+            ..
+        } => {
+            map.insert("manufacturer_id", byte_list(manufacturer_id));
+            map.insert("family", *family);
+            map.insert("member", *member);
+            map.insert("version", byte_list(version));
+        }
+        UniversalSysEx::MasterVolume { volume, .. } => map.insert("volume", *volume),
+        UniversalSysEx::MasterBalance { balance, .. } => map.insert("balance", *balance),
+        UniversalSysEx::MasterFineTuning { fine_tuning, .. } => {
+            map.insert("fine_tuning", *fine_tuning)
+        }
+        UniversalSysEx::MasterCoarseTuning { coarse_tuning, .. } => {
+            map.insert("coarse_tuning", *coarse_tuning)
+        }
+        _ => {}
+    }
+    map
+}
+
+/// The fields of a system exclusive message which the library knows, `None` for others.
+pub fn koto_sysex_detail(message: &SystemExclusive) -> Option<KMap> {
+    if let Some(message) = UniversalSysEx::from_sysex(message) {
+        return Some(koto_universal_sysex(&message));
+    }
+    if let Some(message) = Mmc::from_sysex(message) {
+        return Some(koto_mmc(&message));
+    }
+    if let Some(message) = Mts::from_sysex(message) {
+        return Some(koto_mts(&message));
+    }
+    let timecode = Timecode::from_full_frame(message)?;
+    let map = KMap::new();
+    map.insert("type", "full_frame");
+    map.insert("device_id", message.message_content()[0]);
+    for (key, value) in koto_timecode(timecode).data().iter() {
+        map.insert(key.clone(), value.clone());
+    }
+    Some(map)
+}
+
+fn koto_sysex(message: UniversalSysEx, strict: bool) -> KValue {
+    KotoMessage::new(message.to_sysex().into())
+        .with_strict(strict)
+        .into()
+}
+
+/// Reads a numeric argument, which is clamped to its range unless the module is strict.
+pub(crate) fn number_arg(
+    value: &KValue,
+    name: &'static str,
+    max: u64,
+    strict: bool,
+    context: &str,
+) -> Result<u64, RuntimeError> {
+    match value {
+        KValue::Number(KNumber::I64(value)) if *value >= 0 => {
+            let value = *value as u64;
+            if strict {
+                if let Err(error) = check_range(name, value, max) {
+                    return runtime_error!("{context}: {error}");
+                }
+            }
+            Ok(value.min(max))
+        }
+        _ => runtime_error!("{context}: {name} must be a non-negative integer"),
+    }
+}
+
+type DeviceMessage = fn(u8) -> UniversalSysEx;
+type ValueMessage = fn(u8, u64) -> UniversalSysEx;
+
+/// Reads the bytes and the optional order name which the packing functions take.
+fn packing_args<T: Default>(
+    args: &[KValue],
+    from_name: fn(&str) -> Option<T>,
+    error: &str,
+) -> Result<(Vec<u8>, T), RuntimeError> {
+    let (bytes, order) = match args {
+        [KValue::List(bytes)] => (bytes, T::default()),
+        [KValue::List(bytes), KValue::Str(name)] => match from_name(name.as_str()) {
+            Some(order) => (bytes, order),
+            None => return runtime_error!(error),
+        },
+        _ => return runtime_error!(error),
+    };
+    Ok((crate::collect_list_of_u8(bytes, error)?, order))
+}
+
+pub fn make_sysex_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+    module.insert("all_devices", ALL_DEVICES);
+
+    let device_messages: [(&'static str, DeviceMessage); 4] = [
+        ("identity_request", |device_id| {
+            UniversalSysEx::IdentityRequest { device_id }
+        }),
+        ("gm1_system_on", |device_id| UniversalSysEx::Gm1SystemOn {
+            device_id,
+        }),
+        ("gm_system_off", |device_id| UniversalSysEx::GmSystemOff {
+            device_id,
+        }),
+        ("gm2_system_on", |device_id| UniversalSysEx::Gm2SystemOn {
+            device_id,
+        }),
+    ];
+    for (name, make_message) in device_messages {
+        module.add_fn(name, move |ctx| match ctx.args() {
+            [device_id] => {
+                let device_id = number_arg(device_id, "device_id", 127, settings.strict, name)?;
+                Ok(koto_sysex(make_message(device_id as u8), settings.strict))
+            }
+            _ => runtime_error!("{name} requires a device id as its argument"),
+        });
+    }
+
+    let value_messages: [(&'static str, &'static str, u64, ValueMessage); 4] = [
+        ("master_volume", "volume", 16383, |device_id, volume| {
+            UniversalSysEx::MasterVolume {
+                device_id,
+                volume: volume as u16,
+            }
+        }),
+        ("master_balance", "balance", 16383, |device_id, balance| {
+            UniversalSysEx::MasterBalance {
+                device_id,
+                balance: balance as u16,
+            }
+        }),
+        (
+            "master_fine_tuning",
+            "fine_tuning",
+            16383,
+            |device_id, fine_tuning| UniversalSysEx::MasterFineTuning {
+                device_id,
+                fine_tuning: fine_tuning as u16,
+            },
+        ),
+        (
+            "master_coarse_tuning",
+            "coarse_tuning",
+            127,
+            |device_id, coarse_tuning| UniversalSysEx::MasterCoarseTuning {
+                device_id,
+                coarse_tuning: coarse_tuning as u8,
+            },
+        ),
+    ];
+    for (name, value_name, max, make_message) in value_messages {
+        module.add_fn(name, move |ctx| match ctx.args() {
+            [device_id, value] => {
+                let device_id = number_arg(device_id, "device_id", 127, settings.strict, name)?;
+                let value = number_arg(value, value_name, max, settings.strict, name)?;
+                Ok(koto_sysex(
+                    make_message(device_id as u8, value),
+                    settings.strict,
+                ))
+            }
+            _ => runtime_error!("{name} requires a device id and a {value_name} as its arguments"),
+        });
+    }
+
+    module.add_fn("identity_reply", move |ctx| {
+        let context = "identity_reply";
+        let error = "identity_reply requires a device id and a map of the manufacturer_id, \
+                     family, member and version as its arguments";
+        let [device_id, KValue::Map(fields)] = ctx.args() else {
+            return runtime_error!(error);
+        };
+        let device_id = number_arg(device_id, "device_id", 127, settings.strict, context)?;
+        let bytes = |name: &str| match fields.get(name) {
+            Some(KValue::List(bytes)) => crate::collect_list_of_midi_bytes_as_u8(&bytes, error),
+            _ => runtime_error!(error),
+        };
+        let manufacturer_id = bytes("manufacturer_id")?;
+        let Ok(version) = <[u8; 4]>::try_from(bytes("version")?) else {
+            return runtime_error!("{context}: version must be a list of 4 bytes");
+        };
+        check_manufacturer_id(&manufacturer_id, context)?;
+        let number = |name: &'static str| match fields.get(name) {
+            Some(value) => number_arg(&value, name, 16383, settings.strict, context),
+            None => Ok(0),
+        };
+        let message = UniversalSysEx::IdentityReply {
+            device_id: device_id as u8,
+            manufacturer_id,
+            family: number("family")? as u16,
+            member: number("member")? as u16,
+            version,
+        };
+        Ok(koto_sysex(message, settings.strict))
+    });
+
+    module.add_fn("manufacturer", |ctx| {
+        let error = "manufacturer requires a manufacturer id as its argument";
+        let [KValue::List(id)] = ctx.args() else {
+            return runtime_error!(error);
+        };
+        let id = crate::collect_list_of_u8(id, error)?;
+        let Some(region) = manufacturer::region(&id) else {
+            return Ok(KValue::Null);
+        };
+        let map = KMap::new();
+        map.insert("id", byte_list(&id));
+        match manufacturer::name(&id) {
+            Some(name) => map.insert("name", name),
+            None => map.insert("name", KValue::Null),
+        }
+        map.insert("region", region.name());
+        Ok(map.into())
+    });
+
+    module.add_fn("pack7", |ctx| {
+        let error = "pack7 requires a list of bytes, and optionally \"msb_first\" or \
+                     \"lsb_first\", as its arguments";
+        let (data, order) = packing_args(ctx.args(), BitOrder::from_name, error)?;
+        Ok(byte_list(&packing::pack7(&data, order)))
+    });
+
+    module.add_fn("unpack7", |ctx| {
+        let error = "unpack7 requires a list of bytes, and optionally \"msb_first\" or \
+                     \"lsb_first\", as its arguments";
+        let (packed, order) = packing_args(ctx.args(), BitOrder::from_name, error)?;
+        match packing::unpack7(&packed, order) {
+            Some(data) => Ok(byte_list(&data)),
+            None => runtime_error!("unpack7: packed data must only have data bytes"),
+        }
+    });
+
+    module.add_fn("nibblize", |ctx| {
+        let error = "nibblize requires a list of bytes, and optionally \"high_first\" or \
+                     \"low_first\", as its arguments";
+        let (data, order) = packing_args(ctx.args(), NibbleOrder::from_name, error)?;
+        Ok(byte_list(&packing::nibblize(&data, order)))
+    });
+
+    module.add_fn("denibblize", |ctx| {
+        let error = "denibblize requires a list of bytes, and optionally \"high_first\" or \
+                     \"low_first\", as its arguments";
+        let (nibbles, order) = packing_args(ctx.args(), NibbleOrder::from_name, error)?;
+        match packing::denibblize(&nibbles, order) {
+            Some(data) => Ok(byte_list(&data)),
+            None => runtime_error!(
+                "denibblize: nibblized data must have an even number of bytes from 0 to 0x0F"
+            ),
+        }
+    });
+
+    module.add_fn("assembler", move |ctx| {
+        let error = "assembler takes an optional map of the max_size and the timeout as its \
+                     argument";
+        let mut assembler = SysexAssembler::new();
+        match ctx.args() {
+            [] => {}
+            [KValue::Map(fields)] => {
+                let limit = |name: &str| match fields.get(name) {
+                    Some(KValue::Number(KNumber::I64(value))) if value > 0 => {
+                        Ok(Some(value as usize))
+                    }
+                    None | Some(KValue::Null) => Ok(None),
+                    Some(_) => runtime_error!("assembler: {name} must be a positive integer"),
+                };
+                if let Some(max_size) = limit("max_size")? {
+                    assembler = assembler.with_max_size(max_size);
+                }
+                if let Some(timeout) = limit("timeout")? {
+                    assembler = assembler.with_timeout(timeout);
+                }
+            }
+            _ => return runtime_error!(error),
+        }
+        let assembler = KotoSysexAssembler::new(assembler).with_strict(settings.strict);
+        Ok(KValue::Object(assembler.into()))
+    });
+
+    module.add_fn("split", |ctx| {
+        let error = "split requires a system exclusive message and a positive packet size as \
+                     its arguments";
+        let [KValue::Object(message), KValue::Number(KNumber::I64(size))] = ctx.args() else {
+            return runtime_error!(error);
+        };
+        if *size <= 0 || !message.is_a::<KotoMessage>() {
+            return runtime_error!(error);
+        }
+        let Message::SystemExclusive(message) = message.cast::<KotoMessage>()?.message().clone()
+        else {
+            return runtime_error!(error);
+        };
+        let packets = dump::split(&message, *size as usize)
+            .iter()
+            .map(|packet| byte_list(packet))
+            .collect::<Vec<KValue>>();
+        Ok(KValue::List(KList::from_slice(&packets[..])))
+    });
+
+    module.add_fn("parse", |ctx| {
+        let error = "parse requires a system exclusive message or a list of bytes as its argument";
+        let message = match ctx.args() {
+            [KValue::Object(message)] if message.is_a::<KotoMessage>() => {
+                message.cast::<KotoMessage>()?.message().clone()
+            }
+            [KValue::List(bytes)] => {
+                ParsedMessage::from(&crate::collect_list_of_u8(bytes, error)?[..]).message
+            }
+            _ => return runtime_error!(error),
+        };
+        match message {
+            Message::SystemExclusive(message) => match koto_sysex_detail(&message) {
+                Some(detail) => Ok(detail.into()),
+                None => Ok(KValue::Null),
+            },
+            _ => Ok(KValue::Null),
+        }
+    });
+
+    module
+}
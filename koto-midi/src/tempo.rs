@@ -0,0 +1,300 @@
+//! Conversions between ticks, seconds, bars and beats under changing tempo and time signature.
+
+use crate::file::{EventKind, MidiFile, Timing};
+use crate::message::*;
+
+/// A position in bars and beats.
+///
+/// Bars and beats count from 1 like they do in notation, the tick is the offset into the beat.
+/// A beat is a note of the time signature's denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BarBeatTick {
+    pub bar: u64,
+    pub beat: u64,
+    pub tick: u64,
+}
+
+impl BarBeatTick {
+    pub fn new(bar: u64, beat: u64, tick: u64) -> Self {
+        Self { bar, beat, tick }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TempoChange {
+    tick: u64,
+    tempo: Tempo,
+    /// The seconds elapsed before the change.
+    seconds: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimeSignatureChange {
+    tick: u64,
+    time_signature: TimeSignature,
+    /// The bars started before the change, counting from 0.
+    bar: u64,
+}
+
+/// The tempo and time signature changes of a sequence.
+///
+/// Until the first change the tempo is 120 beats per minute in 4/4, as the standard midi file
+/// specification defines. A change at the tick of an earlier one replaces it.
+///
+/// A time signature change which does not fall on a bar line starts a new bar, the bar before it
+/// is left short.
+#[derive(Debug, Clone)]
+pub struct TempoMap {
+    ticks_per_quarter_note: u16,
+    tempos: Vec<TempoChange>,
+    time_signatures: Vec<TimeSignatureChange>,
+}
+
+impl TempoMap {
+    pub fn new(ticks_per_quarter_note: u16) -> Self {
+        Self {
+            ticks_per_quarter_note: ticks_per_quarter_note.max(1),
+            tempos: vec![TempoChange {
+                tick: 0,
+                tempo: Tempo::default(),
+                seconds: 0.0,
+            }],
+            time_signatures: vec![TimeSignatureChange {
+                tick: 0,
+                time_signature: TimeSignature::default(),
+                bar: 0,
+            }],
+        }
+    }
+
+    /// Collects the tempo and time signature changes of all tracks of a file.
+    ///
+    /// Returns `None` for files with SMPTE timing, their ticks are fractions of frames which
+    /// do not depend on the tempo.
+    pub fn from_file(file: &MidiFile) -> Option<Self> {
+        let Timing::TicksPerQuarterNote(ticks_per_quarter_note) = file.header.timing else {
+            return None;
+        };
+        // The changes are added in the order of their ticks, so each one is appended to the map.
+        // The sort is stable, so a later change at the same tick still replaces an earlier one.
+        let mut changes = file
+            .tracks
+            .iter()
+            .flatten()
+            .filter_map(|event| match &event.kind {
+                EventKind::Message(message @ (Message::Tempo(_) | Message::TimeSignature(_))) => {
+                    Some((event.tick, message))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|(tick, _)| *tick);
+        let mut map = Self::new(ticks_per_quarter_note);
+        for (tick, message) in changes {
+            map.add(tick, message);
+        }
+        Some(map)
+    }
+
+    pub fn ticks_per_quarter_note(&self) -> u16 {
+        self.ticks_per_quarter_note
+    }
+
+    /// Adds a tempo or a time signature change, other messages are ignored and return false.
+    pub fn add(&mut self, tick: u64, message: &Message) -> bool {
+        match message {
+            Message::Tempo(tempo) => self.add_tempo(tick, *tempo),
+            Message::TimeSignature(time_signature) => {
+                self.add_time_signature(tick, *time_signature)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn add_tempo(&mut self, tick: u64, tempo: Tempo) {
+        let change = TempoChange {
+            tick,
+            tempo,
+            seconds: 0.0,
+        };
+        let index = match self
+            .tempos
+            .binary_search_by_key(&tick, |change| change.tick)
+        {
+            Ok(index) => {
+                self.tempos[index] = change;
+                index
+            }
+            Err(index) => {
+                self.tempos.insert(index, change);
+                index
+            }
+        };
+        // Only the changes from the new one on depend on it.
+        for index in index.max(1)..self.tempos.len() {
+            let previous = self.tempos[index - 1];
+            self.tempos[index].seconds = previous.seconds
+                + self.seconds_per_tick(&previous.tempo)
+                    * (self.tempos[index].tick - previous.tick) as f64;
+        }
+    }
+
+    pub fn add_time_signature(&mut self, tick: u64, time_signature: TimeSignature) {
+        let change = TimeSignatureChange {
+            tick,
+            time_signature,
+            bar: 0,
+        };
+        let index = match self
+            .time_signatures
+            .binary_search_by_key(&tick, |change| change.tick)
+        {
+            Ok(index) => {
+                self.time_signatures[index] = change;
+                index
+            }
+            Err(index) => {
+                self.time_signatures.insert(index, change);
+                index
+            }
+        };
+        for index in index.max(1)..self.time_signatures.len() {
+            let previous = self.time_signatures[index - 1];
+            let ticks_per_bar = self.ticks_per_bar(&previous.time_signature);
+            self.time_signatures[index].bar = previous.bar
+                + (self.time_signatures[index].tick - previous.tick).div_ceil(ticks_per_bar);
+        }
+    }
+
+    /// The tempo which is in effect at a tick.
+    pub fn tempo_at(&self, tick: u64) -> Tempo {
+        self.tempo_change_at(tick).tempo
+    }
+
+    /// The time signature which is in effect at a tick.
+    pub fn time_signature_at(&self, tick: u64) -> TimeSignature {
+        self.time_signature_change_at(tick).time_signature
+    }
+
+    pub fn ticks_to_seconds(&self, tick: u64) -> f64 {
+        let change = self.tempo_change_at(tick);
+        change.seconds + self.seconds_per_tick(&change.tempo) * (tick - change.tick) as f64
+    }
+
+    /// The nearest tick to a time in seconds, times before the start are at tick 0.
+    pub fn seconds_to_ticks(&self, seconds: f64) -> u64 {
+        if seconds.is_nan() || seconds <= 0.0 {
+            return 0;
+        }
+        let index = self
+            .tempos
+            .partition_point(|change| change.seconds <= seconds);
+        let change = &self.tempos[index - 1];
+        let seconds_per_tick = self.seconds_per_tick(&change.tempo);
+        if seconds_per_tick == 0.0 {
+            return change.tick;
+        }
+        change
+            .tick
+            .saturating_add(((seconds - change.seconds) / seconds_per_tick).round() as u64)
+    }
+
+    pub fn ticks_to_bar_beat_tick(&self, tick: u64) -> BarBeatTick {
+        let change = self.time_signature_change_at(tick);
+        let ticks_per_beat = self.ticks_per_beat(&change.time_signature);
+        let ticks_per_bar = self.ticks_per_bar(&change.time_signature);
+        let offset = tick - change.tick;
+        BarBeatTick {
+            bar: change.bar + offset / ticks_per_bar + 1,
+            beat: offset % ticks_per_bar / ticks_per_beat + 1,
+            tick: offset % ticks_per_bar % ticks_per_beat,
+        }
+    }
+
+    /// The tick of a position in bars and beats, bars and beats below 1 are treated as 1.
+    ///
+    /// Beats and ticks beyond the end of a bar carry over into the following bars,
+    /// positions beyond the last tick saturate at `u64::MAX`.
+    pub fn bar_beat_tick_to_ticks(&self, position: BarBeatTick) -> u64 {
+        let bar = position.bar.max(1) - 1;
+        let index = self
+            .time_signatures
+            .partition_point(|change| change.bar <= bar);
+        let change = &self.time_signatures[index - 1];
+        change
+            .tick
+            .saturating_add(
+                (bar - change.bar).saturating_mul(self.ticks_per_bar(&change.time_signature)),
+            )
+            .saturating_add(
+                (position.beat.max(1) - 1)
+                    .saturating_mul(self.ticks_per_beat(&change.time_signature)),
+            )
+            .saturating_add(position.tick)
+    }
+
+    /// Midi beats are the sixteenth notes which song position pointers count.
+    ///
+    /// Ticks beyond the last number of midi beats saturate at `u64::MAX`.
+    pub fn ticks_to_midi_beats(&self, tick: u64) -> u64 {
+        u64::try_from(tick as u128 * 4 / self.ticks_per_quarter_note as u128).unwrap_or(u64::MAX)
+    }
+
+    /// Numbers of midi beats beyond the last tick saturate at `u64::MAX`.
+    pub fn midi_beats_to_ticks(&self, midi_beats: u64) -> u64 {
+        u64::try_from(midi_beats as u128 * self.ticks_per_quarter_note as u128 / 4)
+            .unwrap_or(u64::MAX)
+    }
+
+    /// The song position of the last midi beat at or before a tick.
+    ///
+    /// Ticks beyond the last song position are clamped to it.
+    pub fn ticks_to_song_position(&self, tick: u64) -> SongPosition {
+        SongPosition::new(self.ticks_to_midi_beats(tick))
+    }
+
+    /// Like [TempoMap::ticks_to_song_position], but ticks beyond the last song position are an
+    /// error.
+    pub fn try_ticks_to_song_position(&self, tick: u64) -> Result<SongPosition, RangeError> {
+        SongPosition::try_new(self.ticks_to_midi_beats(tick))
+    }
+
+    pub fn song_position_to_seconds(&self, song_position: &SongPosition) -> f64 {
+        self.ticks_to_seconds(self.midi_beats_to_ticks(song_position.midi_beats_elapsed() as u64))
+    }
+
+    fn tempo_change_at(&self, tick: u64) -> &TempoChange {
+        // The first change is always at tick 0.
+        let index = self.tempos.partition_point(|change| change.tick <= tick);
+        &self.tempos[index - 1]
+    }
+
+    fn time_signature_change_at(&self, tick: u64) -> &TimeSignatureChange {
+        let index = self
+            .time_signatures
+            .partition_point(|change| change.tick <= tick);
+        &self.time_signatures[index - 1]
+    }
+
+    fn seconds_per_tick(&self, tempo: &Tempo) -> f64 {
+        tempo.microseconds_per_quarter_note() as f64
+            / 1_000_000.0
+            / self.ticks_per_quarter_note as f64
+    }
+
+    fn ticks_per_beat(&self, time_signature: &TimeSignature) -> u64 {
+        (self.ticks_per_quarter_note as u64 * 4 / time_signature.denominator() as u64).max(1)
+    }
+
+    fn ticks_per_bar(&self, time_signature: &TimeSignature) -> u64 {
+        self.ticks_per_beat(time_signature) * time_signature.numerator().max(1) as u64
+    }
+}
+
+impl Default for TempoMap {
+    /// 480 ticks per quarter note.
+    fn default() -> Self {
+        Self::new(480)
+    }
+}
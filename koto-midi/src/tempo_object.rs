@@ -0,0 +1,200 @@
+use crate::message::*;
+use crate::tempo::*;
+use crate::KotoMessage;
+
+use koto::derive::*;
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+pub fn koto_bar_beat_tick(position: BarBeatTick) -> KMap {
+    let map = KMap::new();
+    map.insert("bar", position.bar);
+    map.insert("beat", position.beat);
+    map.insert("tick", position.tick);
+    map
+}
+
+fn tick_arg(args: &[KValue], error: &str) -> Result<u64, RuntimeError> {
+    match args {
+        [KValue::Number(KNumber::I64(tick))] if *tick >= 0 => Ok(*tick as u64),
+        _ => runtime_error!(error),
+    }
+}
+
+/// Ticks and midi beats which koto integers can't hold are runtime errors.
+fn tick_value(tick: u64, error: &str) -> Result<KValue, RuntimeError> {
+    match i64::try_from(tick) {
+        Ok(tick) => Ok(tick.into()),
+        Err(_) => runtime_error!(error),
+    }
+}
+
+/// A tempo map which can be used from koto scripts.
+#[derive(Clone, KotoType, KotoCopy)]
+#[koto(type_name = "TempoMap")]
+pub struct KotoTempoMap {
+    map: TempoMap,
+    strict: bool,
+}
+
+impl KotoTempoMap {
+    pub fn new(map: TempoMap) -> Self {
+        Self { map, strict: false }
+    }
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+#[koto_impl]
+impl KotoTempoMap {
+    #[koto_method]
+    fn add(ctx: MethodContext<Self>) -> Result<KValue, RuntimeError> {
+        let error = "add requires a tick and a tempo or time signature message as its arguments";
+        match ctx.args {
+            [KValue::Number(KNumber::I64(tick)), KValue::Object(message)]
+                if *tick >= 0 && message.is_a::<KotoMessage>() =>
+            {
+                let message = message.cast::<KotoMessage>()?;
+                if !ctx.instance_mut()?.map.add(*tick as u64, message.message()) {
+                    return runtime_error!(error);
+                }
+                ctx.instance_result()
+            }
+            _ => runtime_error!(error),
+        }
+    }
+
+    #[koto_method]
+    fn ticks_per_quarter_note(&self) -> KValue {
+        self.map.ticks_per_quarter_note().into()
+    }
+
+    #[koto_method]
+    fn tempo_at(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let tick = tick_arg(args, "tempo_at requires a tick as its argument")?;
+        Ok(KotoMessage::new(self.map.tempo_at(tick).into())
+            .with_strict(self.strict)
+            .into())
+    }
+
+    #[koto_method]
+    fn time_signature_at(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let tick = tick_arg(args, "time_signature_at requires a tick as its argument")?;
+        Ok(KotoMessage::new(self.map.time_signature_at(tick).into())
+            .with_strict(self.strict)
+            .into())
+    }
+
+    #[koto_method]
+    fn ticks_to_seconds(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let tick = tick_arg(args, "ticks_to_seconds requires a tick as its argument")?;
+        Ok(self.map.ticks_to_seconds(tick).into())
+    }
+
+    #[koto_method]
+    fn seconds_to_ticks(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        match args {
+            [KValue::Number(seconds)] => tick_value(
+                self.map.seconds_to_ticks(seconds.into()),
+                "seconds_to_ticks can't represent a time this late in ticks",
+            ),
+            _ => runtime_error!("seconds_to_ticks requires a number of seconds as its argument"),
+        }
+    }
+
+    #[koto_method]
+    fn ticks_to_bar_beat_tick(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let tick = tick_arg(
+            args,
+            "ticks_to_bar_beat_tick requires a tick as its argument",
+        )?;
+        Ok(koto_bar_beat_tick(self.map.ticks_to_bar_beat_tick(tick)).into())
+    }
+
+    #[koto_method]
+    fn bar_beat_tick_to_ticks(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let error = "bar_beat_tick_to_ticks requires a bar, a beat and a tick, \
+                     or a map of them as its arguments";
+        let number = |value: Option<KValue>, default: u64| match value {
+            Some(KValue::Number(KNumber::I64(value))) if value >= 0 => Ok(value as u64),
+            None => Ok(default),
+            _ => runtime_error!(error),
+        };
+        let position = match args {
+            [KValue::Map(position)] => BarBeatTick::new(
+                number(position.get("bar"), 1)?,
+                number(position.get("beat"), 1)?,
+                number(position.get("tick"), 0)?,
+            ),
+            [bar, beat, tick] => BarBeatTick::new(
+                number(Some(bar.clone()), 1)?,
+                number(Some(beat.clone()), 1)?,
+                number(Some(tick.clone()), 0)?,
+            ),
+            _ => return runtime_error!(error),
+        };
+        tick_value(
+            self.map.bar_beat_tick_to_ticks(position),
+            "bar_beat_tick_to_ticks can't represent a position this late in ticks",
+        )
+    }
+
+    #[koto_method]
+    fn ticks_to_midi_beats(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let tick = tick_arg(args, "ticks_to_midi_beats requires a tick as its argument")?;
+        tick_value(
+            self.map.ticks_to_midi_beats(tick),
+            "ticks_to_midi_beats can't represent a tick this late in midi beats",
+        )
+    }
+
+    #[koto_method]
+    fn midi_beats_to_ticks(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let midi_beats = tick_arg(
+            args,
+            "midi_beats_to_ticks requires a number of midi beats as its argument",
+        )?;
+        tick_value(
+            self.map.midi_beats_to_ticks(midi_beats),
+            "midi_beats_to_ticks can't represent this many midi beats in ticks",
+        )
+    }
+
+    #[koto_method]
+    fn ticks_to_song_position(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        let tick = tick_arg(
+            args,
+            "ticks_to_song_position requires a tick as its argument",
+        )?;
+        let song_position = if self.strict {
+            match self.map.try_ticks_to_song_position(tick) {
+                Ok(song_position) => song_position,
+                Err(error) => return runtime_error!("ticks_to_song_position: {error}"),
+            }
+        } else {
+            self.map.ticks_to_song_position(tick)
+        };
+        Ok(KotoMessage::new(song_position.into())
+            .with_strict(self.strict)
+            .into())
+    }
+
+    #[koto_method]
+    fn song_position_to_seconds(&self, args: &[KValue]) -> Result<KValue, RuntimeError> {
+        match args {
+            [KValue::Object(message)] if message.is_a::<KotoMessage>() => {
+                match message.cast::<KotoMessage>()?.message() {
+                    Message::SongPosition(song_position) => {
+                        Ok(self.map.song_position_to_seconds(song_position).into())
+                    }
+                    _ => runtime_error!("song_position_to_seconds requires a song position"),
+                }
+            }
+            _ => runtime_error!("song_position_to_seconds requires a song position"),
+        }
+    }
+}
+
+impl KotoObject for KotoTempoMap {}
@@ -0,0 +1,260 @@
+//! The parameter messages of Roland and Yamaha devices, which are system exclusive messages with
+//! an address and a checksum.
+//!
+//! Roland messages are `F0 41 <device id> <model id> <command> <address> <data> <checksum> F7`,
+//! where the command is 0x12 for data set (DT1) and 0x11 for data request (RQ1). The length of
+//! the model id and the address depend on the device, so they are given when a message is read.
+//!
+//! Yamaha parameter changes are `F0 43 1n <model id> <address> <data> F7` and bulk dumps are
+//! `F0 43 0n <model id> <byte count> <address> <data> <checksum> F7`, where n is the device
+//! number.
+
+use crate::message::*;
+
+pub const ROLAND: u8 = 0x41;
+pub const YAMAHA: u8 = 0x43;
+
+/// The checksum which Roland and Yamaha devices use, the low 7 bits of the sum of the checksummed
+/// bytes and the checksum are 0.
+pub fn checksum(bytes: &[u8]) -> u8 {
+    let sum = bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(byte & 0x7F));
+    0u8.wrapping_sub(sum) & 0x7F
+}
+
+pub fn is_checksum_valid(bytes: &[u8], checksum: u8) -> bool {
+    self::checksum(bytes) == checksum
+}
+
+/// Splits a value into the given number of 7 bit bytes, most significant first.
+pub fn split_7_bit(value: u64, length: usize) -> Vec<u8> {
+    (0..length)
+        .rev()
+        .map(|index| (value.checked_shr(7 * index as u32).unwrap_or(0) & 0x7F) as u8)
+        .collect()
+}
+
+fn data_bytes(bytes: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    bytes.iter().map(|byte| byte & 0x7F)
+}
+
+const DT1: u8 = 0x12;
+const RQ1: u8 = 0x11;
+
+/// A Roland parameter message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Roland {
+    /// Requests `size` bytes from the address, the size has as many bytes as the address.
+    Rq1 {
+        device_id: u8,
+        model_id: Vec<u8>,
+        address: Vec<u8>,
+        size: Vec<u8>,
+    },
+    /// Sets the data at the address.
+    Dt1 {
+        device_id: u8,
+        model_id: Vec<u8>,
+        address: Vec<u8>,
+        data: Vec<u8>,
+    },
+}
+
+impl Roland {
+    pub fn device_id(&self) -> u8 {
+        match self {
+            Roland::Rq1 { device_id, .. } | Roland::Dt1 { device_id, .. } => *device_id,
+        }
+    }
+
+    pub fn model_id(&self) -> &[u8] {
+        match self {
+            Roland::Rq1 { model_id, .. } | Roland::Dt1 { model_id, .. } => model_id,
+        }
+    }
+
+    pub fn address(&self) -> &[u8] {
+        match self {
+            Roland::Rq1 { address, .. } | Roland::Dt1 { address, .. } => address,
+        }
+    }
+
+    /// The bytes which the checksum is computed from, the address and the data or size.
+    fn checksummed(&self) -> Vec<u8> {
+        let (Roland::Rq1 {
+            address,
+            size: data,
+            ..
+        }
+        | Roland::Dt1 { address, data, .. }) = self;
+        data_bytes(address).chain(data_bytes(data)).collect()
+    }
+
+    pub fn to_sysex(&self) -> SystemExclusive {
+        let command = match self {
+            Roland::Rq1 { .. } => RQ1,
+            Roland::Dt1 { .. } => DT1,
+        };
+        let checksummed = self.checksummed();
+        let mut content = vec![self.device_id() & 0x7F];
+        content.extend(data_bytes(self.model_id()));
+        content.push(command);
+        content.extend(&checksummed);
+        content.push(checksum(&checksummed));
+        SystemExclusive::new(&[ROLAND], &content)
+    }
+
+    /// Reads a message with a model id and an address of the given lengths, along with whether
+    /// its checksum is valid. Other messages return `None`.
+    pub fn from_sysex(
+        message: &SystemExclusive,
+        model_id_length: usize,
+        address_length: usize,
+    ) -> Option<(Self, bool)> {
+        if message.manufacturer_id() != [ROLAND] {
+            return None;
+        }
+        let (device_id, content) = message.message_content().split_first()?;
+        if content.len() < model_id_length + 1 + address_length + 1 {
+            return None;
+        }
+        let (model_id, content) = content.split_at(model_id_length);
+        let (command, content) = content.split_first()?;
+        let (checksum, checksummed) = content.split_last()?;
+        let (address, data) = checksummed.split_at(address_length);
+        let message = match *command {
+            DT1 => Roland::Dt1 {
+                device_id: *device_id,
+                model_id: model_id.to_vec(),
+                address: address.to_vec(),
+                data: data.to_vec(),
+            },
+            RQ1 if data.len() == address_length => Roland::Rq1 {
+                device_id: *device_id,
+                model_id: model_id.to_vec(),
+                address: address.to_vec(),
+                size: data.to_vec(),
+            },
+            _ => return None,
+        };
+        Some((message, is_checksum_valid(checksummed, *checksum)))
+    }
+}
+
+impl From<&Roland> for SystemExclusive {
+    fn from(message: &Roland) -> Self {
+        message.to_sysex()
+    }
+}
+
+const PARAMETER_CHANGE: u8 = 0x10;
+const BULK_DUMP: u8 = 0x00;
+
+/// A Yamaha parameter message, the device number is sent in the low 4 bits of the byte after
+/// the manufacturer id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Yamaha {
+    /// Sets the data at the address, it has no checksum.
+    ParameterChange {
+        device_number: u8,
+        model_id: u8,
+        address: Vec<u8>,
+        data: Vec<u8>,
+    },
+    /// Sends a block of data with its 14 bit byte count and a checksum.
+    BulkDump {
+        device_number: u8,
+        model_id: u8,
+        address: Vec<u8>,
+        data: Vec<u8>,
+    },
+}
+
+impl Yamaha {
+    pub fn device_number(&self) -> u8 {
+        match self {
+            Yamaha::ParameterChange { device_number, .. }
+            | Yamaha::BulkDump { device_number, .. } => *device_number,
+        }
+    }
+
+    pub fn to_sysex(&self) -> SystemExclusive {
+        let content = match self {
+            Yamaha::ParameterChange {
+                device_number,
+                model_id,
+                address,
+                data,
+            } => [PARAMETER_CHANGE | (device_number & 0x0F), model_id & 0x7F]
+                .into_iter()
+                .chain(data_bytes(address))
+                .chain(data_bytes(data))
+                .collect(),
+            Yamaha::BulkDump {
+                device_number,
+                model_id,
+                address,
+                data,
+            } => {
+                let checksummed = split_7_bit(data.len().min(0x3FFF) as u64, 2)
+                    .into_iter()
+                    .chain(data_bytes(address))
+                    .chain(data_bytes(&data[..data.len().min(0x3FFF)]))
+                    .collect::<Vec<u8>>();
+                let mut content = vec![BULK_DUMP | (device_number & 0x0F), model_id & 0x7F];
+                content.extend(&checksummed);
+                content.push(checksum(&checksummed));
+                content
+            }
+        };
+        SystemExclusive::new(&[YAMAHA], &content)
+    }
+
+    /// Reads a message with an address of the given length, along with whether its checksum is
+    /// valid. Parameter changes have no checksum so they are always valid, other messages return
+    /// `None`.
+    pub fn from_sysex(message: &SystemExclusive, address_length: usize) -> Option<(Self, bool)> {
+        if message.manufacturer_id() != [YAMAHA] {
+            return None;
+        }
+        let [status, model_id, content @ ..] = message.message_content() else {
+            return None;
+        };
+        let device_number = status & 0x0F;
+        match status & 0x70 {
+            PARAMETER_CHANGE if content.len() >= address_length => {
+                let (address, data) = content.split_at(address_length);
+                let message = Yamaha::ParameterChange {
+                    device_number,
+                    model_id: *model_id,
+                    address: address.to_vec(),
+                    data: data.to_vec(),
+                };
+                Some((message, true))
+            }
+            BULK_DUMP if content.len() > 2 + address_length => {
+                let (checksum, checksummed) = content.split_last()?;
+                let (count, content) = checksummed.split_at(2);
+                let (address, data) = content.split_at(address_length);
+                if ((count[0] as usize) << 7 | count[1] as usize) != data.len() {
+                    return None;
+                }
+                let message = Yamaha::BulkDump {
+                    device_number,
+                    model_id: *model_id,
+                    address: address.to_vec(),
+                    data: data.to_vec(),
+                };
+                Some((message, is_checksum_valid(checksummed, *checksum)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<&Yamaha> for SystemExclusive {
+    fn from(message: &Yamaha) -> Self {
+        message.to_sysex()
+    }
+}
@@ -0,0 +1,238 @@
+use crate::message::*;
+use crate::message_object::byte_list;
+use crate::sysex_module::number_arg;
+use crate::vendor::*;
+use crate::KotoMessage;
+use crate::ModuleSettings;
+
+use koto::prelude::*;
+use koto::Error as RuntimeError;
+
+fn koto_vendor_message(message: SystemExclusive, strict: bool) -> KValue {
+    KotoMessage::new(message.into()).with_strict(strict).into()
+}
+
+/// Reads a list of bytes, which must be data bytes when the module is strict.
+fn bytes_arg(
+    value: &KValue,
+    name: &str,
+    strict: bool,
+    context: &str,
+) -> Result<Vec<u8>, RuntimeError> {
+    let error = format!("{context}: {name} must be a list of bytes");
+    match value {
+        KValue::List(bytes) if strict => crate::collect_list_of_midi_bytes_as_u8(bytes, &error),
+        KValue::List(bytes) => crate::collect_list_of_u8(bytes, &error),
+        _ => runtime_error!(error),
+    }
+}
+
+/// Reads the system exclusive message of a message object or a list of bytes.
+fn sysex_arg(value: &KValue, error: &str) -> Result<Option<SystemExclusive>, RuntimeError> {
+    let message = match value {
+        KValue::Object(message) if message.is_a::<KotoMessage>() => {
+            message.cast::<KotoMessage>()?.message().clone()
+        }
+        KValue::List(bytes) => {
+            ParsedMessage::from(&crate::collect_list_of_u8(bytes, error)?[..]).message
+        }
+        _ => return runtime_error!(error),
+    };
+    match message {
+        Message::SystemExclusive(message) => Ok(Some(message)),
+        _ => Ok(None),
+    }
+}
+
+fn length_arg(value: Option<&KValue>, default: usize, error: &str) -> Result<usize, RuntimeError> {
+    match value {
+        None => Ok(default),
+        Some(KValue::Number(KNumber::I64(length))) if (1..=4).contains(length) => {
+            Ok(*length as usize)
+        }
+        Some(_) => runtime_error!(error),
+    }
+}
+
+fn checksum_fn(ctx: &mut CallContext) -> Result<KValue, RuntimeError> {
+    let error = "checksum requires a list of bytes as its argument";
+    match ctx.args() {
+        [KValue::List(bytes)] => Ok(checksum(&crate::collect_list_of_u8(bytes, error)?).into()),
+        _ => runtime_error!(error),
+    }
+}
+
+pub fn koto_roland(message: &Roland, valid: bool) -> KMap {
+    let map = KMap::new();
+    map.insert("device_id", message.device_id());
+    map.insert("model_id", byte_list(message.model_id()));
+    map.insert("address", byte_list(message.address()));
+    match message {
+        Roland::Rq1 { size, .. } => {
+            map.insert("command", "rq1");
+            map.insert("size", byte_list(size));
+        }
+        Roland::Dt1 { data, .. } => {
+            map.insert("command", "dt1");
+            map.insert("data", byte_list(data));
+        }
+    }
+    map.insert("valid", valid);
+    map
+}
+
+pub fn koto_yamaha(message: &Yamaha, valid: bool) -> KMap {
+    let map = KMap::new();
+    let (type_literal, model_id, address, data) = match message {
+        Yamaha::ParameterChange {
+            model_id,
+            address,
+            data,
+            ..
+        } => ("parameter_change", model_id, address, data),
+        Yamaha::BulkDump {
+            model_id,
+            address,
+            data,
+            ..
+        } => ("bulk_dump", model_id, address, data),
+    };
+    map.insert("type", type_literal);
+    map.insert("device_number", message.device_number());
+    map.insert("model_id", *model_id);
+    map.insert("address", byte_list(address));
+    map.insert("data", byte_list(data));
+    map.insert("valid", valid);
+    map
+}
+
+pub fn make_roland_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+
+    module.add_fn("checksum", checksum_fn);
+
+    module.add_fn("dt1", move |ctx| {
+        let context = "dt1";
+        let [device_id, model_id, address, data] = ctx.args() else {
+            return runtime_error!(
+                "dt1 requires a device id, a model id, an address and a list of data bytes as \
+                 its arguments"
+            );
+        };
+        let message = Roland::Dt1 {
+            device_id: number_arg(device_id, "device_id", 127, settings.strict, context)? as u8,
+            model_id: bytes_arg(model_id, "model_id", settings.strict, context)?,
+            address: bytes_arg(address, "address", settings.strict, context)?,
+            data: bytes_arg(data, "data", settings.strict, context)?,
+        };
+        Ok(koto_vendor_message(message.to_sysex(), settings.strict))
+    });
+
+    module.add_fn("rq1", move |ctx| {
+        let context = "rq1";
+        let [device_id, model_id, address, size] = ctx.args() else {
+            return runtime_error!(
+                "rq1 requires a device id, a model id, an address and a size as its arguments"
+            );
+        };
+        let address = bytes_arg(address, "address", settings.strict, context)?;
+        // A size can also be given as a number, which is split into as many bytes as the address.
+        let max = 1u64
+            .checked_shl(7 * address.len() as u32)
+            .map_or(u64::MAX, |limit| limit - 1);
+        let size = match size {
+            KValue::Number(_) => split_7_bit(
+                number_arg(size, "size", max, settings.strict, context)?,
+                address.len(),
+            ),
+            size => bytes_arg(size, "size", settings.strict, context)?,
+        };
+        let message = Roland::Rq1 {
+            device_id: number_arg(device_id, "device_id", 127, settings.strict, context)? as u8,
+            model_id: bytes_arg(model_id, "model_id", settings.strict, context)?,
+            address,
+            size,
+        };
+        Ok(koto_vendor_message(message.to_sysex(), settings.strict))
+    });
+
+    module.add_fn("parse", |ctx| {
+        let error = "parse requires a message or a list of bytes, and optionally the lengths of \
+                     the model id and the address, as its arguments";
+        let args = ctx.args();
+        if args.is_empty() || args.len() > 3 {
+            return runtime_error!(error);
+        }
+        let model_id_length = length_arg(args.get(1), 1, error)?;
+        let address_length = length_arg(args.get(2), 3, error)?;
+        let Some(message) = sysex_arg(&args[0], error)? else {
+            return Ok(KValue::Null);
+        };
+        match Roland::from_sysex(&message, model_id_length, address_length) {
+            Some((message, valid)) => Ok(koto_roland(&message, valid).into()),
+            None => Ok(KValue::Null),
+        }
+    });
+
+    module
+}
+
+pub fn make_yamaha_module(settings: ModuleSettings) -> KMap {
+    let module = KMap::new();
+
+    module.add_fn("checksum", checksum_fn);
+
+    for (name, bulk_dump) in [("parameter_change", false), ("bulk_dump", true)] {
+        module.add_fn(name, move |ctx| {
+            let [device_number, model_id, address, data] = ctx.args() else {
+                return runtime_error!(
+                    "{name} requires a device number, a model id, an address and a list of \
+                     data bytes as its arguments"
+                );
+            };
+            let device_number =
+                number_arg(device_number, "device_number", 15, settings.strict, name)? as u8;
+            let model_id = number_arg(model_id, "model_id", 127, settings.strict, name)? as u8;
+            let address = bytes_arg(address, "address", settings.strict, name)?;
+            let data = bytes_arg(data, "data", settings.strict, name)?;
+            if settings.strict && bulk_dump && data.len() > 0x3FFF {
+                return runtime_error!("{name}: a bulk dump can have up to 16383 data bytes");
+            }
+            let message = if bulk_dump {
+                Yamaha::BulkDump {
+                    device_number,
+                    model_id,
+                    address,
+                    data,
+                }
+            } else {
+                Yamaha::ParameterChange {
+                    device_number,
+                    model_id,
+                    address,
+                    data,
+                }
+            };
+            Ok(koto_vendor_message(message.to_sysex(), settings.strict))
+        });
+    }
+
+    module.add_fn("parse", |ctx| {
+        let error = "parse requires a message or a list of bytes, and optionally the length of \
+                     the address, as its arguments";
+        let args = ctx.args();
+        if args.is_empty() || args.len() > 2 {
+            return runtime_error!(error);
+        }
+        let address_length = length_arg(args.get(1), 3, error)?;
+        let Some(message) = sysex_arg(&args[0], error)? else {
+            return Ok(KValue::Null);
+        };
+        match Yamaha::from_sysex(&message, address_length) {
+            Some((message, valid)) => Ok(koto_yamaha(&message, valid).into()),
+            None => Ok(KValue::Null),
+        }
+    });
+
+    module
+}
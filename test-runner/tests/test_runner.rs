@@ -1,9 +1,15 @@
 use {
     koto::{Koto, KotoSettings},
+    koto_midi::ModuleSettings,
     std::{fs::read_to_string, path::PathBuf},
 };
 
-fn run_script(script: &str, path: Option<PathBuf>, should_fail_at_runtime: bool) {
+fn run_script(
+    script: &str,
+    path: Option<PathBuf>,
+    settings: ModuleSettings,
+    should_fail_at_runtime: bool,
+) {
     let mut koto = Koto::with_settings(KotoSettings {
         run_tests: true,
         ..Default::default()
@@ -11,7 +17,7 @@ fn run_script(script: &str, path: Option<PathBuf>, should_fail_at_runtime: bool)
     koto.set_script_path(path).unwrap();
 
     let prelude = koto.prelude();
-    let module = koto_midi::make_module();
+    let module = koto_midi::make_module_with_settings(settings);
     prelude.insert("midi", module);
 
     match koto.compile(script) {
@@ -33,7 +39,7 @@ fn run_script(script: &str, path: Option<PathBuf>, should_fail_at_runtime: bool)
     }
 }
 
-fn load_and_run_script(script_path: &str) {
+fn load_and_run_script(script_path: &str, settings: ModuleSettings) {
     let mut path = PathBuf::new();
     path.push(env!("CARGO_MANIFEST_DIR"));
     path.push("../koto/tests");
@@ -44,14 +50,17 @@ fn load_and_run_script(script_path: &str) {
     let script =
         read_to_string(&path).unwrap_or_else(|_| panic!("Unable to load path '{:?}'", &path));
 
-    run_script(&script, Some(path), false);
+    run_script(&script, Some(path), settings, false);
 }
 
 macro_rules! module_test {
     ($name:ident) => {
+        module_test!($name, ModuleSettings::default());
+    };
+    ($name:ident, $settings:expr) => {
         #[test]
         fn $name() {
-            load_and_run_script(&format!("{}.koto", stringify!($name)));
+            load_and_run_script(&format!("{}.koto", stringify!($name)), $settings);
         }
     };
 }
@@ -60,4 +69,18 @@ mod lib_tests {
     use super::*;
     module_test!(midi);
     module_test!(api);
+    module_test!(parser);
+    module_test!(controller);
+    module_test!(file);
+    module_test!(file_roundtrip);
+    module_test!(meta);
+    module_test!(tempo);
+    module_test!(mtc);
+    module_test!(sysex);
+    module_test!(mmc);
+    module_test!(mts);
+    module_test!(scala);
+    module_test!(vendor);
+    module_test!(dump);
+    module_test!(strict, ModuleSettings { strict: true });
 }